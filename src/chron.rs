@@ -0,0 +1,423 @@
+// A small Chronicler v2 client. It splits large id lists into batches, runs a bounded number of
+// requests at once, and retries requests that fail for reasons that might go away on their own.
+// It can also serve the same queries from a directory of fixture files or from local data dumps,
+// for running offline.
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt, TryStreamExt};
+use itertools::Itertools;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use uuid::Uuid;
+use crate::chron_dump::ChronDump;
+
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+// Chronicler accepts more than this, but the URL gets unwieldy
+const MAX_IDS_PER_REQUEST: usize = 50;
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChronEntity<T> {
+    // The aliases are for data dumps, which use the database column names
+    #[serde(alias = "entity_id")]
+    pub entity_id: Uuid,
+    #[serde(alias = "valid_from")]
+    pub valid_from: DateTime<Utc>,
+    pub data: T,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChronPage<T> {
+    next_page: Option<String>,
+    items: Vec<ChronEntity<T>>,
+}
+
+enum Backend {
+    Http {
+        client: reqwest::Client,
+        // The v2 API root, without a trailing slash
+        endpoint: String,
+    },
+    // A directory with one file per entity, at {type}/{id}.json, holding a JSON array of every
+    // version of that entity in the same shape the API returns them. Versions can be in any order.
+    Fixtures(PathBuf),
+    Dump(ChronDump),
+}
+
+pub struct ChronClient {
+    backend: Backend,
+}
+
+impl ChronClient {
+    pub fn new(client: reqwest::Client, endpoint: &str) -> Self {
+        Self {
+            backend: Backend::Http {
+                client,
+                endpoint: endpoint.trim_end_matches('/').to_string(),
+            },
+        }
+    }
+
+    pub fn from_fixtures(dir: impl Into<PathBuf>) -> Self {
+        Self { backend: Backend::Fixtures(dir.into()) }
+    }
+
+    pub fn from_dump(dir: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        Ok(Self { backend: Backend::Dump(ChronDump::open(dir.into())?) })
+    }
+
+    fn is_local(&self) -> bool {
+        !matches!(self.backend, Backend::Http { .. })
+    }
+
+    // Every version of this entity that a local backend has, sorted by time
+    fn local_history<T: DeserializeOwned>(&self, ty: &str, id: Uuid) -> anyhow::Result<Vec<ChronEntity<T>>> {
+        match &self.backend {
+            Backend::Http { .. } => unreachable!("HTTP clients don't have local history"),
+            Backend::Fixtures(dir) => fixture_history(dir, ty, id),
+            Backend::Dump(dump) => dump.history(ty, id),
+        }
+    }
+
+    // Every entity of this type a local backend has any version of. Chronicler has far too many
+    // to list, so HTTP clients return None.
+    pub fn local_ids(&self, ty: &str) -> anyhow::Result<Option<Vec<Uuid>>> {
+        match &self.backend {
+            Backend::Http { .. } => Ok(None),
+            Backend::Fixtures(dir) => fixture_ids(dir, ty).map(Some),
+            Backend::Dump(dump) => dump.ids(ty).map(Some),
+        }
+    }
+
+    fn local_entities<T: DeserializeOwned>(&self, ty: &str, ids: &[Uuid], at: DateTime<Utc>) -> anyhow::Result<Vec<ChronEntity<T>>> {
+        let mut entities = Vec::new();
+        for &id in ids {
+            let history = self.local_history(ty, id)?;
+            entities.extend(history.into_iter().take_while(|version| version.valid_from <= at).last());
+        }
+        Ok(entities)
+    }
+
+    fn local_versions<T: DeserializeOwned>(&self, ty: &str, ids: &[Uuid], after: DateTime<Utc>, before: DateTime<Utc>) -> anyhow::Result<Vec<ChronEntity<T>>> {
+        let mut versions = Vec::new();
+        for &id in ids {
+            versions.extend(self.local_history(ty, id)?.into_iter()
+                .filter(|version| after < version.valid_from && version.valid_from < before));
+        }
+        Ok(versions)
+    }
+
+    // Every entity of this type with one of these ids, as it was at `at`
+    pub async fn entities<T: DeserializeOwned>(&self, ty: &str, ids: &[Uuid], at: DateTime<Utc>) -> anyhow::Result<Vec<ChronEntity<T>>> {
+        if self.is_local() {
+            return self.local_entities(ty, ids, at);
+        }
+        stream::iter(ids.chunks(MAX_IDS_PER_REQUEST))
+            .map(|batch| self.entities_batch(ty, batch, at))
+            .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+            .try_concat()
+            .await
+    }
+
+    fn endpoint(&self) -> &str {
+        match &self.backend {
+            Backend::Http { endpoint, .. } => endpoint,
+            _ => unreachable!("Local clients don't make requests"),
+        }
+    }
+
+    async fn entities_batch<T: DeserializeOwned>(&self, ty: &str, ids: &[Uuid], at: DateTime<Utc>) -> anyhow::Result<Vec<ChronEntity<T>>> {
+        let url = format!("{}/entities", self.endpoint());
+        self.get_all_pages(&url, vec![
+            ("type", ty.to_string()),
+            ("id", ids.iter().join(",")),
+            ("at", at.to_rfc3339()),
+        ]).await
+    }
+
+    // Every version of every entity of this type with one of these ids that became valid after
+    // `after` and before `before`. This doesn't include the version that was already valid at
+    // `after`; use entities() for that.
+    pub async fn versions<T: DeserializeOwned>(&self, ty: &str, ids: &[Uuid], after: DateTime<Utc>, before: DateTime<Utc>) -> anyhow::Result<Vec<ChronEntity<T>>> {
+        if self.is_local() {
+            return self.local_versions(ty, ids, after, before);
+        }
+        stream::iter(ids.chunks(MAX_IDS_PER_REQUEST))
+            .map(|batch| self.versions_batch(ty, batch, after, before))
+            .buffer_unordered(MAX_CONCURRENT_REQUESTS)
+            .try_concat()
+            .await
+    }
+
+    async fn versions_batch<T: DeserializeOwned>(&self, ty: &str, ids: &[Uuid], after: DateTime<Utc>, before: DateTime<Utc>) -> anyhow::Result<Vec<ChronEntity<T>>> {
+        let url = format!("{}/versions", self.endpoint());
+        self.get_all_pages(&url, vec![
+            ("type", ty.to_string()),
+            ("id", ids.iter().join(",")),
+            ("after", after.to_rfc3339()),
+            ("before", before.to_rfc3339()),
+            ("order", "asc".to_string()),
+        ]).await
+    }
+
+    async fn get_all_pages<T: DeserializeOwned>(&self, url: &str, mut query: Vec<(&str, String)>) -> anyhow::Result<Vec<ChronEntity<T>>> {
+        let mut entities = Vec::new();
+        loop {
+            let page: ChronPage<T> = self.get_with_retry(url, &query).await?;
+            let page_len = page.items.len();
+            entities.extend(page.items);
+            match page.next_page {
+                Some(next_page) if page_len > 0 => {
+                    query.retain(|(key, _)| *key != "page");
+                    query.push(("page", next_page));
+                }
+                _ => break,
+            }
+        }
+
+        Ok(entities)
+    }
+
+    async fn get_with_retry<T: DeserializeOwned>(&self, url: &str, query: &[(&str, String)]) -> anyhow::Result<T> {
+        let Backend::Http { client, .. } = &self.backend else {
+            unreachable!("Local clients don't make requests")
+        };
+        let mut delay = INITIAL_RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            let result = async {
+                client.get(url)
+                    .query(query)
+                    .send().await?
+                    .error_for_status()?
+                    .text().await
+            }.await;
+
+            match result {
+                Ok(text) => return Ok(serde_json::from_str(&text)?),
+                Err(e) if attempt < MAX_ATTEMPTS && is_transient(&e) => {
+                    println!("Request to {url} failed ({e}), retrying in {delay:?}");
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+fn is_transient(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect() ||
+        e.status().is_some_and(|status| status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS)
+}
+
+fn fixture_ids(dir: &Path, ty: &str) -> anyhow::Result<Vec<Uuid>> {
+    let dir = dir.join(ty);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut ids = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+            ids.push(stem.parse()
+                .with_context(|| format!("Fixture {} isn't named after an entity id", path.display()))?);
+        }
+    }
+    Ok(ids)
+}
+
+// Every version of this entity in the fixture directory, sorted by time. Entities without a file
+// have no versions, the same as entities Chronicler doesn't know about.
+fn fixture_history<T: DeserializeOwned>(dir: &Path, ty: &str, id: Uuid) -> anyhow::Result<Vec<ChronEntity<T>>> {
+    let path = dir.join(ty).join(format!("{id}.json"));
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Couldn't read fixture {}", path.display()))?;
+    let mut versions: Vec<ChronEntity<T>> = serde_json::from_str(&text)
+        .with_context(|| format!("Couldn't parse fixture {}", path.display()))?;
+    versions.sort_by_key(|version| version.valid_from);
+    Ok(versions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use chrono::TimeZone;
+    use serde_json::json;
+    use super::*;
+
+    // A stand-in for Chronicler on a local port. Every request's query is recorded, decoded, and
+    // handed to `respond`, which returns the status and body to send back.
+    struct TestServer {
+        endpoint: String,
+        requests: Arc<Mutex<Vec<Vec<(String, String)>>>>,
+    }
+
+    impl TestServer {
+        fn start(respond: impl Fn(usize, &[(String, String)]) -> (u16, String) + Send + 'static) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let endpoint = format!("http://{}/v2", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let server_requests = requests.clone();
+            // Never joined. It goes away with the test process.
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let mut reader = BufReader::new(&mut stream);
+                    let mut request_line = String::new();
+                    reader.read_line(&mut request_line).unwrap();
+                    // Skip the headers. GETs don't have a body.
+                    let mut header = String::new();
+                    while reader.read_line(&mut header).unwrap() > 2 {
+                        header.clear();
+                    }
+
+                    let target = request_line.split(' ').nth(1).unwrap_or_default();
+                    let query = target.split_once('?')
+                        .map(|(_, query)| parse_query(query))
+                        .unwrap_or_default();
+                    let index = {
+                        let mut requests = server_requests.lock().unwrap();
+                        requests.push(query.clone());
+                        requests.len() - 1
+                    };
+                    let (status, body) = respond(index, &query);
+                    write!(stream, "HTTP/1.1 {status} Test\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}", body.len()).unwrap();
+                }
+            });
+            Self { endpoint, requests }
+        }
+
+        fn client(&self) -> ChronClient {
+            ChronClient::new(reqwest::Client::new(), &self.endpoint)
+        }
+
+        fn requests(&self) -> Vec<Vec<(String, String)>> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    fn parse_query(query: &str) -> Vec<(String, String)> {
+        query.split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (percent_decode(key), percent_decode(value)))
+            .collect()
+    }
+
+    fn percent_decode(s: &str) -> String {
+        let bytes = s.as_bytes();
+        let mut decoded = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' && i + 2 < bytes.len() {
+                decoded.push(u8::from_str_radix(&s[i + 1..i + 3], 16).unwrap());
+                i += 3;
+            } else {
+                decoded.push(if bytes[i] == b'+' { b' ' } else { bytes[i] });
+                i += 1;
+            }
+        }
+        String::from_utf8(decoded).unwrap()
+    }
+
+    fn param<'a>(query: &'a [(String, String)], key: &str) -> Option<&'a str> {
+        query.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn entity(id: Uuid) -> serde_json::Value {
+        json!({ "entityId": id, "validFrom": "2021-04-12T15:00:00Z", "data": {} })
+    }
+
+    fn page(next_page: Option<&str>, items: Vec<serde_json::Value>) -> (u16, String) {
+        (200, json!({ "nextPage": next_page, "items": items }).to_string())
+    }
+
+    fn time() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2021, 4, 12, 15, 0, 0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn follows_pages_until_one_is_empty() {
+        let (first, second) = (Uuid::from_u128(1), Uuid::from_u128(2));
+        let server = TestServer::start(move |_, query| match param(query, "page") {
+            None => page(Some("p2"), vec![entity(first)]),
+            Some("p2") => page(Some("p3"), vec![entity(second)]),
+            // Chronicler hands out a next page token even when there's nothing left
+            _ => page(Some("p4"), vec![]),
+        });
+
+        let versions = server.client()
+            .versions::<serde_json::Value>("player", &[first, second], time(), time() + chrono::Duration::hours(1))
+            .await.unwrap();
+        assert_eq!(versions.iter().map(|version| version.entity_id).collect_vec(), vec![first, second]);
+
+        let requests = server.requests();
+        assert_eq!(requests.iter().map(|query| param(query, "page")).collect_vec(), vec![None, Some("p2"), Some("p3")]);
+        // Every page asks for the same thing
+        assert!(requests.iter().all(|query| param(query, "type") == Some("player") && param(query, "order") == Some("asc")));
+    }
+
+    #[tokio::test]
+    async fn splits_ids_into_batches() {
+        let server = TestServer::start(|_, query| {
+            let items = param(query, "id").unwrap().split(',')
+                .map(|id| entity(id.parse().unwrap()))
+                .collect();
+            page(None, items)
+        });
+
+        let ids = (0..120).map(Uuid::from_u128).collect_vec();
+        let entities = server.client()
+            .entities::<serde_json::Value>("player", &ids, time())
+            .await.unwrap();
+        assert_eq!(entities.iter().map(|entity| entity.entity_id).sorted().collect_vec(), ids);
+
+        let batch_sizes = server.requests().iter()
+            .map(|query| param(query, "id").unwrap().split(',').count())
+            .sorted()
+            .collect_vec();
+        assert_eq!(batch_sizes, vec![20, MAX_IDS_PER_REQUEST, MAX_IDS_PER_REQUEST]);
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let id = Uuid::from_u128(1);
+        let server = TestServer::start(move |index, _| match index {
+            0 => (500, String::new()),
+            1 => (503, String::new()),
+            _ => page(None, vec![entity(id)]),
+        });
+
+        let entities = server.client()
+            .entities::<serde_json::Value>("player", &[id], time())
+            .await.unwrap();
+        assert_eq!(entities.len(), 1);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_client_errors() {
+        let server = TestServer::start(|_, _| (404, String::new()));
+
+        let result = server.client()
+            .entities::<serde_json::Value>("player", &[Uuid::from_u128(1)], time())
+            .await;
+        assert!(result.is_err());
+        assert_eq!(server.requests().len(), 1);
+    }
+}
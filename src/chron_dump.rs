@@ -0,0 +1,151 @@
+// Reads entity versions from local copies of the SIBR Chronicler data dumps. The dump directory
+// has one file per entity type, named after the type Chronicler uses (team.ndjson, player.json,
+// ...). NDJSON files have one version per line and JSON files hold an array of versions. Either
+// way, each version has the same shape the v2 API returns.
+//
+// Dumps are large, so files are streamed a version at a time and each version is kept as compact
+// JSON text rather than a parsed tree. Only team, player and stadium files are required. Without
+// the sim, league, subleague and division files there's no league structure to walk down, so the
+// world store falls back to every team in the dump.
+use std::collections::HashMap;
+use std::fs::File;
+use std::fmt::Formatter;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use anyhow::{anyhow, Context};
+use serde::de::{DeserializeOwned, SeqAccess, Visitor};
+use serde::Deserializer;
+use uuid::Uuid;
+use crate::chron::ChronEntity;
+
+// Each version's data is kept as compact JSON text
+type History = Vec<ChronEntity<String>>;
+
+// The types a world can't be built without. The rest of what WorldStore::populate asks for is
+// optional.
+const REQUIRED_TYPES: [&str; 3] = ["team", "player", "stadium"];
+
+pub struct ChronDump {
+    dir: PathBuf,
+    // Each type's file is read the first time something asks for that type, then kept in memory
+    loaded: Mutex<HashMap<String, Arc<HashMap<Uuid, History>>>>,
+}
+
+impl ChronDump {
+    // Fails up front if any of the files a world needs are missing, rather than partway through
+    // fetching one
+    pub fn open(dir: PathBuf) -> anyhow::Result<Self> {
+        let dump = Self {
+            dir,
+            loaded: Mutex::new(HashMap::new()),
+        };
+        let missing = REQUIRED_TYPES.into_iter()
+            .filter(|ty| dump.path_for(ty).is_none())
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            return Err(anyhow!("Dump directory {} is missing {}. Building a world needs a .ndjson or .json file for each of {}",
+                dump.dir.display(), missing.join(", "), REQUIRED_TYPES.join(", ")));
+        }
+        Ok(dump)
+    }
+
+    // The file holding this type, preferring NDJSON
+    fn path_for(&self, ty: &str) -> Option<PathBuf> {
+        [format!("{ty}.ndjson"), format!("{ty}.json")].into_iter()
+            .map(|name| self.dir.join(name))
+            .find(|path| path.exists())
+    }
+
+    // Every version of this entity in the dump, sorted by time
+    pub fn history<T: DeserializeOwned>(&self, ty: &str, id: Uuid) -> anyhow::Result<Vec<ChronEntity<T>>> {
+        let histories = self.histories(ty)?;
+        let Some(history) = histories.get(&id) else { return Ok(Vec::new()) };
+        history.iter()
+            .map(|version| Ok(ChronEntity {
+                entity_id: version.entity_id,
+                valid_from: version.valid_from,
+                data: serde_json::from_str(&version.data)
+                    .with_context(|| format!("Couldn't parse {ty} {id} from {}", version.valid_from))?,
+            }))
+            .collect()
+    }
+
+    // Every entity of this type the dump has any version of
+    pub fn ids(&self, ty: &str) -> anyhow::Result<Vec<Uuid>> {
+        Ok(self.histories(ty)?.keys().copied().collect())
+    }
+
+    fn histories(&self, ty: &str) -> anyhow::Result<Arc<HashMap<Uuid, History>>> {
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some(histories) = loaded.get(ty) {
+            return Ok(histories.clone());
+        }
+
+        let histories = Arc::new(self.load(ty)?);
+        loaded.insert(ty.to_string(), histories.clone());
+        Ok(histories)
+    }
+
+    fn load(&self, ty: &str) -> anyhow::Result<HashMap<Uuid, History>> {
+        // Types that aren't required just have no versions when their file is missing
+        let Some(path) = self.path_for(ty) else { return Ok(HashMap::new()) };
+
+        let mut histories: HashMap<Uuid, History> = HashMap::new();
+        let mut count = 0;
+        let mut add = |version: ChronEntity<serde_json::Value>| -> anyhow::Result<()> {
+            histories.entry(version.entity_id).or_default().push(ChronEntity {
+                entity_id: version.entity_id,
+                valid_from: version.valid_from,
+                data: serde_json::to_string(&version.data)?,
+            });
+            count += 1;
+            Ok(())
+        };
+        let reader = BufReader::new(File::open(&path)?);
+        if path.extension().is_some_and(|ext| ext == "ndjson") {
+            for version in serde_json::Deserializer::from_reader(reader).into_iter::<ChronEntity<serde_json::Value>>() {
+                add(version.with_context(|| format!("Couldn't parse dump {}", path.display()))?)?;
+            }
+        } else {
+            read_json_array(&path, reader, &mut add)?;
+        }
+        println!("Loaded {count} {ty} versions from dump");
+
+        for history in histories.values_mut() {
+            history.sort_by_key(|version| version.valid_from);
+            // Dumps taken at different times overlap, so the same version can appear twice. Values
+            // serialize with sorted keys, so equal data means equal text.
+            history.dedup_by(|a, b| a.valid_from == b.valid_from && a.data == b.data);
+        }
+        Ok(histories)
+    }
+}
+
+// Hands each version in a JSON array to `add` as it's parsed, so the whole array is never in
+// memory at once
+fn read_json_array<R: std::io::Read>(path: &Path, reader: R, add: &mut dyn FnMut(ChronEntity<serde_json::Value>) -> anyhow::Result<()>) -> anyhow::Result<()> {
+    struct EachVersion<'a> {
+        add: &'a mut dyn FnMut(ChronEntity<serde_json::Value>) -> anyhow::Result<()>,
+    }
+
+    impl<'de> Visitor<'de> for EachVersion<'_> {
+        type Value = ();
+
+        fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+            write!(f, "an array of entity versions")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+            while let Some(version) = seq.next_element()? {
+                (self.add)(version).map_err(serde::de::Error::custom)?;
+            }
+            Ok(())
+        }
+    }
+
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    (&mut deserializer).deserialize_seq(EachVersion { add })
+        .and_then(|()| deserializer.end())
+        .with_context(|| format!("Couldn't parse dump {}", path.display()))
+}
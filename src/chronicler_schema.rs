@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
+use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
+use itertools::Itertools;
+use crate::mods::{Mod, ModLayer};
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
@@ -55,14 +58,14 @@ pub struct Team {
     pub stadium: Option<Uuid>,
     pub deceased: Option<bool>,
     pub full_name: String,
-    pub game_attr: Vec<String>,
+    pub game_attr: Vec<Mod>,
     pub league_id: Option<Uuid>,
     pub location: String,
     pub nickname: String,
-    pub perm_attr: Vec<String>,
+    pub perm_attr: Vec<Mod>,
     pub rotation: Vec<Uuid>,
-    pub seas_attr: Vec<String>,
-    pub week_attr: Vec<String>,
+    pub seas_attr: Vec<Mod>,
+    pub week_attr: Vec<Mod>,
     pub evolution: Option<i32>,
     pub main_color: String,
     pub shame_runs: f32,
@@ -95,14 +98,218 @@ impl Display for Team {
     }
 }
 
+// Never seen this populated in the expansion era, so it's not worth modeling yet
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
-pub struct Item {
-    // TODO Implement Item, reinstate deny_unknown_fields
+pub struct StadiumState {}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+pub struct Stadium {
+    pub id: Uuid,
+    pub name: String,
+    pub nickname: String,
+    pub model: Option<i32>,
+    pub team_id: Uuid,
+    pub main_color: String,
+    pub secondary_color: String,
+    pub tertiary_color: String,
+    pub mods: Vec<Mod>,
+    pub birds: i32,
+    pub state: Option<StadiumState>,
+
+    pub hype: f64,
+    pub mysticism: f64,
+    pub viscosity: f64,
+    pub elongation: f64,
+    pub filthiness: f64,
+    pub obtuseness: f64,
+    pub forwardness: f64,
+    pub grandiosity: f64,
+    pub ominousness: f64,
+    pub fortification: f64,
+    pub inconvenience: f64,
+    pub luxuriousness: f64,
+
+    pub reno_cost: f64,
+    pub reno_log: HashMap<String, i32>,
+    pub reno_hand: Vec<String>,
+    pub reno_discard: Vec<String>,
+    pub weather: HashMap<String, i32>,
+}
+
+impl Stadium {
+    pub fn has_mod(&self, m: Mod) -> bool {
+        self.mods.contains(&m)
+    }
+
+    pub fn unknown_mods(&self) -> impl Iterator<Item=&str> {
+        self.mods.iter()
+            .filter_map(|m| match m {
+                Mod::Unknown(name) => Some(name.as_str()),
+                _ => None,
+            })
+    }
 }
 
+impl Display for Stadium {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Stadium: {}", self.name)
+    }
+}
+
+// Every player stat, numbered the way Chronicler numbers them in item adjustments
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum Stat {
+    Tragicness = 0,
+    Buoyancy,
+    Thwackability,
+    Moxie,
+    Divinity,
+    Musclitude,
+    Patheticism,
+    Martyrdom,
+    Cinnamon,
+    BaseThirst,
+    Laserlikeness,
+    Continuation,
+    Indulgence,
+    GroundFriction,
+    Shakespearianism,
+    Suppression,
+    Unthwackability,
+    Coldness,
+    Overpowerment,
+    Ruthlessness,
+    Pressurization,
+    Omniscience,
+    Tenaciousness,
+    Watchfulness,
+    Anticapitalism,
+    Chasiness,
+}
+
+// Chronicler stores adjustments as a `type` discriminant next to the fields for that type. This is
+// the flat form, which (unlike an untagged enum) also round-trips through bincode.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct RawItemAdjustment {
+    #[serde(rename = "type")]
+    ty: i32,
+    #[serde(rename = "mod")]
+    mod_name: Option<Mod>,
+    stat: Option<usize>,
+    value: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "RawItemAdjustment", into = "RawItemAdjustment")]
+pub enum ItemAdjustment {
+    Mod(Mod),
+    Stat { stat: usize, value: f64 },
+}
+
+impl TryFrom<RawItemAdjustment> for ItemAdjustment {
+    type Error = String;
+
+    fn try_from(raw: RawItemAdjustment) -> Result<Self, Self::Error> {
+        match raw {
+            RawItemAdjustment { ty: 0, mod_name: Some(m), stat: None, value: None } => {
+                Ok(ItemAdjustment::Mod(m))
+            }
+            RawItemAdjustment { ty: 1, mod_name: None, stat: Some(stat), value: Some(value) } => {
+                Ok(ItemAdjustment::Stat { stat, value })
+            }
+            other => Err(format!("Unexpected item adjustment {other:?}")),
+        }
+    }
+}
+
+impl From<ItemAdjustment> for RawItemAdjustment {
+    fn from(adjustment: ItemAdjustment) -> Self {
+        match adjustment {
+            ItemAdjustment::Mod(m) => RawItemAdjustment { ty: 0, mod_name: Some(m), stat: None, value: None },
+            ItemAdjustment::Stat { stat, value } => RawItemAdjustment { ty: 1, mod_name: None, stat: Some(stat), value: Some(value) },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemElement {
+    pub name: String,
+    pub adjustments: Vec<ItemAdjustment>,
+}
+
+// Never seen this populated in the expansion era, so it's not worth modeling yet
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ItemState {}
+
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "camelCase")]
+#[allow(dead_code)]
+pub struct Item {
+    pub id: Uuid,
+    pub name: String,
+    pub forger: Option<Uuid>,
+    pub forger_name: Option<String>,
+    pub pre_prefix: Option<ItemElement>,
+    pub post_prefix: Option<ItemElement>,
+    pub root: ItemElement,
+    pub prefixes: Option<Vec<ItemElement>>,
+    pub suffix: Option<ItemElement>,
+    // -1 means unbreakable
+    pub durability: i32,
+    pub health: i32,
+    pub state: Option<ItemState>,
+
+    pub defense_rating: Option<f32>,
+    pub hitting_rating: Option<f32>,
+    pub pitching_rating: Option<f32>,
+    pub baserunning_rating: Option<f32>,
+}
+
+impl Item {
+    pub fn is_broken(&self) -> bool {
+        self.durability >= 0 && self.health <= 0
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item=&ItemElement> {
+        self.pre_prefix.iter()
+            .chain(self.post_prefix.iter())
+            .chain(self.prefixes.iter().flatten())
+            .chain(std::iter::once(&self.root))
+            .chain(self.suffix.iter())
+    }
+
+    pub fn adjustments(&self) -> impl Iterator<Item=&ItemAdjustment> {
+        self.elements().flat_map(|element| element.adjustments.iter())
+    }
+
+    pub fn granted_mods(&self) -> impl Iterator<Item=&Mod> {
+        self.adjustments().filter_map(|adjustment| match adjustment {
+            ItemAdjustment::Mod(m) => Some(m),
+            ItemAdjustment::Stat { .. } => None,
+        })
+    }
+
+    pub fn stat_adjustment(&self, stat: Stat) -> f64 {
+        self.adjustments()
+            .filter_map(|adjustment| match adjustment {
+                ItemAdjustment::Stat { stat: index, value } if *index == stat as usize => Some(*value),
+                _ => None,
+            })
+            .sum()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
 pub struct PlayerState {
     pub cut_this_election: Option<bool>,
     pub necromancied_this_election: Option<bool>,
@@ -154,11 +361,11 @@ pub struct Player {
     pub hit_streak: Option<i32>,
     pub consecutive_hits: Option<i32>,
 
-    pub game_attr: Option<Vec<String>>,
-    pub week_attr: Option<Vec<String>>,
-    pub seas_attr: Option<Vec<String>>,
-    pub item_attr: Option<Vec<String>>,
-    pub perm_attr: Option<Vec<String>>,
+    pub game_attr: Option<Vec<Mod>>,
+    pub week_attr: Option<Vec<Mod>>,
+    pub seas_attr: Option<Vec<Mod>>,
+    pub item_attr: Option<Vec<Mod>>,
+    pub perm_attr: Option<Vec<Mod>>,
 
     pub buoyancy: f64,
     pub cinnamon: Option<f64>,
@@ -205,17 +412,272 @@ impl Display for Player {
     }
 }
 
-fn list_has_mod(list: &Option<Vec<String>>, mod_name: &str) -> bool {
-    list.as_ref().is_some_and(|attrs| attrs.iter().any(|attr| attr == mod_name))
+impl Player {
+    pub fn items(&self) -> &[Item] {
+        self.items.as_deref().unwrap_or_default()
+    }
+
+    // Broken items don't do anything
+    pub fn working_items(&self) -> impl Iterator<Item=&Item> {
+        self.items().iter().filter(|item| !item.is_broken())
+    }
+
+    pub fn item_stat_bonus(&self, stat: Stat) -> f64 {
+        self.working_items()
+            .map(|item| item.stat_adjustment(stat))
+            .sum()
+    }
+
+    // Takes one point of health off the item, and if that breaks it, takes away its mods
+    pub fn damage_item(&mut self, index: usize) -> anyhow::Result<&Item> {
+        let item = self.items.as_mut()
+            .and_then(|items| items.get_mut(index))
+            .ok_or_else(|| anyhow!("Tried to damage item {index}, but the player doesn't have that many items"))?;
+        item.health -= 1;
+        if item.is_broken() {
+            let item_id = item.id;
+            let granted = item.granted_mods().cloned().collect::<Vec<_>>();
+            for m in granted {
+                self.remove_item_mod(&m, item_id);
+            }
+        }
+        Ok(&self.items()[index])
+    }
+
+    // Takes the item away from the player, along with any mods it was granting
+    pub fn remove_item(&mut self, index: usize) -> anyhow::Result<Item> {
+        let items = self.items.as_mut()
+            .filter(|items| index < items.len())
+            .ok_or_else(|| anyhow!("Tried to remove item {index}, but the player doesn't have that many items"))?;
+        let item = items.remove(index);
+        for m in item.granted_mods() {
+            self.remove_item_mod(m, item.id);
+        }
+        Ok(item)
+    }
+
+    fn remove_item_mod(&mut self, m: &Mod, item_id: Uuid) {
+        let sources = self.state.as_mut()
+            .and_then(|state| state.item_mod_sources.as_mut())
+            .and_then(|sources| sources.get_mut(m.name()));
+        let still_granted = match sources {
+            Some(sources) => {
+                sources.retain(|id| *id != item_id);
+                !sources.is_empty()
+            }
+            None => false,
+        };
+        if !still_granted {
+            if let Some(attrs) = self.item_attr.as_mut() {
+                if let Some(position) = attrs.iter().position(|attr| attr == m) {
+                    attrs.remove(position);
+                }
+            }
+        }
+    }
+
+    // The item that gave this player a mod in their item_attr, according to item_mod_sources
+    pub fn item_granting_mod(&self, m: &Mod) -> Option<&Item> {
+        let sources = self.state.as_ref()?
+            .item_mod_sources.as_ref()?
+            .get(m.name())?;
+        self.items().iter().find(|item| sources.contains(&item.id))
+    }
+
+    // The name the batter-up message uses for what the batter is holding
+    pub fn wielding_item_name(&self) -> Option<String> {
+        if let Some(item) = self.working_items().next() {
+            return Some(item.name.clone());
+        }
+
+        // Before items, bats were stored as an id like AN_ACTUAL_AIRPLANE
+        let bat = self.bat.as_ref().filter(|bat| !bat.is_empty())?;
+        Some(bat.split('_')
+            .map(|word| {
+                let mut chars = word.chars();
+                match chars.next() {
+                    Some(first) => first.to_string() + &chars.as_str().to_lowercase(),
+                    None => String::new(),
+                }
+            })
+            .join(" "))
+    }
+
+    fn mod_layers(&self) -> [(ModLayer, &[Mod]); 5] {
+        [
+            (ModLayer::Perm, self.perm_attr.as_deref().unwrap_or_default()),
+            (ModLayer::Seas, self.seas_attr.as_deref().unwrap_or_default()),
+            (ModLayer::Week, self.week_attr.as_deref().unwrap_or_default()),
+            (ModLayer::Game, self.game_attr.as_deref().unwrap_or_default()),
+            (ModLayer::Item, self.item_attr.as_deref().unwrap_or_default()),
+        ]
+    }
+
+    // Only looks at the player's own mods. Use World::mod_source to include team and league mods
+    pub fn mod_layer(&self, m: Mod) -> Option<ModLayer> {
+        self.mod_layers().into_iter()
+            .find(|(_, attrs)| attrs.contains(&m))
+            .map(|(layer, _)| layer)
+    }
+
+    pub fn has_mod(&self, m: Mod) -> bool {
+        self.mod_layer(m).is_some()
+    }
+
+    fn layer_mut(&mut self, layer: ModLayer) -> &mut Vec<Mod> {
+        match layer {
+            ModLayer::Perm => self.perm_attr.get_or_insert_with(Vec::new),
+            ModLayer::Seas => self.seas_attr.get_or_insert_with(Vec::new),
+            ModLayer::Week => self.week_attr.get_or_insert_with(Vec::new),
+            ModLayer::Game => self.game_attr.get_or_insert_with(Vec::new),
+            ModLayer::Item => self.item_attr.get_or_insert_with(Vec::new),
+        }
+    }
+
+    // Does nothing if the player already has the mod on that layer
+    pub fn add_mod(&mut self, layer: ModLayer, m: Mod) {
+        let attrs = self.layer_mut(layer);
+        if !attrs.contains(&m) {
+            attrs.push(m);
+        }
+    }
+
+    // Removes the mod from every layer except Item, since item mods go away with their items.
+    // Returns whether the player had it.
+    pub fn remove_mod(&mut self, m: &Mod) -> bool {
+        let mut removed = false;
+        for layer in [ModLayer::Perm, ModLayer::Seas, ModLayer::Week, ModLayer::Game] {
+            let attrs = self.layer_mut(layer);
+            let len_before = attrs.len();
+            attrs.retain(|attr| attr != m);
+            removed |= attrs.len() != len_before;
+        }
+        removed
+    }
+
+    pub fn unknown_mods(&self) -> impl Iterator<Item=&str> {
+        self.mod_layers().into_iter()
+            .flat_map(|(_, attrs)| attrs)
+            .filter_map(|m| match m {
+                Mod::Unknown(name) => Some(name.as_str()),
+                _ => None,
+            })
+    }
+}
+
+impl Team {
+    // Active roster first, then everyone else
+    pub fn all_player_ids(&self) -> impl Iterator<Item=&Uuid> {
+        self.lineup.iter()
+            .chain(self.rotation.iter())
+            .chain(self.shadows.iter().flatten())
+            .chain(self.bench.iter().flatten())
+            .chain(self.bullpen.iter().flatten())
+    }
+
+    // The pitcher whose turn it is in the rotation
+    pub fn active_pitcher(&self) -> Option<Uuid> {
+        if self.rotation.is_empty() { return None; }
+        Some(self.rotation[(self.rotation_slot as usize) % self.rotation.len()])
+    }
+
+    // Puts `new` wherever `old` was on the roster. Returns whether `old` was on it.
+    pub fn replace_player(&mut self, old: Uuid, new: Uuid) -> bool {
+        let rosters = [&mut self.lineup, &mut self.rotation].into_iter()
+            .chain(self.shadows.as_mut())
+            .chain(self.bench.as_mut())
+            .chain(self.bullpen.as_mut());
+        let mut replaced = false;
+        for roster in rosters {
+            for id in roster.iter_mut().filter(|id| **id == old) {
+                *id = new;
+                replaced = true;
+            }
+        }
+        replaced
+    }
+
+    // Trades places between someone on the active roster and someone in the shadows. Returns
+    // whether `shadow` was actually in the shadows.
+    pub fn swap_with_shadow(&mut self, active: Uuid, shadow: Uuid) -> bool {
+        let Some(shadows) = self.shadows.as_mut() else { return false };
+        let Some(position) = shadows.iter().position(|id| *id == shadow) else { return false };
+        shadows[position] = active;
+        for id in self.lineup.iter_mut().chain(self.rotation.iter_mut()).filter(|id| **id == active) {
+            *id = shadow;
+        }
+        true
+    }
+
+    // Takes the player out of the shadows (or the bench or bullpen, in seasons that have those)
+    // so they can be put on the active roster
+    pub fn remove_from_reserves(&mut self, player_id: Uuid) -> bool {
+        let mut removed = false;
+        for roster in [&mut self.shadows, &mut self.bench, &mut self.bullpen].into_iter().flatten() {
+            let len_before = roster.len();
+            roster.retain(|id| *id != player_id);
+            removed |= roster.len() != len_before;
+        }
+        removed
+    }
+
+    fn mod_layers(&self) -> [(ModLayer, &[Mod]); 4] {
+        [
+            (ModLayer::Perm, self.perm_attr.as_slice()),
+            (ModLayer::Seas, self.seas_attr.as_slice()),
+            (ModLayer::Week, self.week_attr.as_slice()),
+            (ModLayer::Game, self.game_attr.as_slice()),
+        ]
+    }
+
+    pub fn mod_layer(&self, m: Mod) -> Option<ModLayer> {
+        self.mod_layers().into_iter()
+            .find(|(_, attrs)| attrs.contains(&m))
+            .map(|(layer, _)| layer)
+    }
+
+    pub fn has_mod(&self, m: Mod) -> bool {
+        self.mod_layer(m).is_some()
+    }
+
+    pub fn unknown_mods(&self) -> impl Iterator<Item=&str> {
+        self.mod_layers().into_iter()
+            .flat_map(|(_, attrs)| attrs)
+            .filter_map(|m| match m {
+                Mod::Unknown(name) => Some(name.as_str()),
+                _ => None,
+            })
+    }
 }
 
-impl Player {
-    pub fn has_mod(&self, mod_name: &str) -> bool {
-        false || // for alignment
-            list_has_mod(&self.perm_attr, mod_name) ||
-            list_has_mod(&self.seas_attr, mod_name) ||
-            list_has_mod(&self.week_attr, mod_name) ||
-            list_has_mod(&self.game_attr, mod_name) ||
-            list_has_mod(&self.item_attr, mod_name)
-    }
-}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+
+    fn team(lineup: &[u128], rotation: &[u128], rotation_slot: i32) -> Team {
+        let ids = |ids: &[u128]| ids.iter().map(|&id| Uuid::from_u128(id)).collect::<Vec<_>>();
+        serde_json::from_value(json!({
+            "id": Uuid::from_u128(100), "emoji": "", "lineup": ids(lineup), "slogan": "",
+            "fullName": "Test Team", "gameAttr": [], "location": "", "nickname": "", "permAttr": [],
+            "rotation": ids(rotation), "seasAttr": [], "weekAttr": [], "mainColor": "",
+            "shameRuns": 0, "shorthand": "", "teamSpirit": 0, "totalShames": 0,
+            "rotationSlot": rotation_slot, "seasonShames": 0, "championships": 0,
+            "totalShamings": 0, "seasonShamings": 0, "secondaryColor": "",
+        })).unwrap()
+    }
+
+    #[test]
+    fn active_pitcher_comes_from_rotation() {
+        // The lineup is longer than the rotation, so reading the wrong one gives a batter
+        let team = team(&[1, 2, 3, 4, 5, 6], &[11, 12, 13], 1);
+        assert_eq!(team.active_pitcher(), Some(Uuid::from_u128(12)));
+    }
+
+    #[test]
+    fn active_pitcher_wraps_rotation_slot() {
+        let team = team(&[1, 2, 3], &[11, 12, 13], 4);
+        assert_eq!(team.active_pitcher(), Some(Uuid::from_u128(12)));
+        assert_eq!(self::team(&[1], &[], 0).active_pitcher(), None);
+    }
+}
@@ -0,0 +1,214 @@
+// Outcome thresholds, loosely following formulas.py in resim. Most of the coefficients here were
+// fit against feed data by other people and haven't been re-checked against this sim yet, so
+// expect to have to tune them when validation starts failing.
+use crate::chronicler_schema::Stadium;
+use crate::stats::{BaserunningStats, BattingStats, DefenseStats, PitchingStats};
+
+#[derive(Debug, Clone)]
+pub struct Ballpark {
+    pub grandiosity: f64,
+    pub fortification: f64,
+    pub obtuseness: f64,
+    pub ominousness: f64,
+    pub inconvenience: f64,
+    pub viscosity: f64,
+    pub forwardness: f64,
+    pub elongation: f64,
+}
+
+impl Ballpark {
+    // A stadium with every attribute at the midpoint has no effect on the pitch and hit formulas,
+    // which are all centred on it. The weather thresholds below take fortification as it is, so
+    // a midpoint stadium still protects a little there.
+    pub const NEUTRAL: Ballpark = Ballpark {
+        grandiosity: 0.5,
+        fortification: 0.5,
+        obtuseness: 0.5,
+        ominousness: 0.5,
+        inconvenience: 0.5,
+        viscosity: 0.5,
+        forwardness: 0.5,
+        elongation: 0.5,
+    };
+}
+
+impl From<&Stadium> for Ballpark {
+    fn from(stadium: &Stadium) -> Self {
+        Ballpark {
+            grandiosity: stadium.grandiosity,
+            fortification: stadium.fortification,
+            obtuseness: stadium.obtuseness,
+            ominousness: stadium.ominousness,
+            inconvenience: stadium.inconvenience,
+            viscosity: stadium.viscosity,
+            forwardness: stadium.forwardness,
+            elongation: stadium.elongation,
+        }
+    }
+}
+
+fn clamp(value: f64, min: f64, max: f64) -> f64 {
+    // Can't "just" use max(min(... because rust cares that NaN isn't totally ordered
+    if value > max { max } else if value < min { min } else { value }
+}
+
+pub fn strike_threshold(batter: &BattingStats, pitcher: &PitchingStats, ballpark: &Ballpark) -> f64 {
+    // NOT EVEN CLOSE TO ACCURATE YET. I just want something that runs
+    0.2 + 0.35 * pitcher.ruthlessness + 0.1 * batter.musclitude + 0.2 * (ballpark.forwardness - 0.5)
+}
+
+pub fn swing_threshold(batter: &BattingStats, pitcher: &PitchingStats, is_strike: bool) -> f64 {
+    // Again: not even close to correct yet
+    if is_strike {
+        let combined_batting = (batter.divinity + batter.musclitude + (1. - batter.patheticism) + batter.thwackability) / 4.;
+        0.7 + 0.35 * combined_batting - 0.4 * pitcher.ruthlessness
+    } else {
+        clamp((12. * pitcher.ruthlessness - 5. * batter.moxie + 5. * batter.patheticism) / 20., 0.1, 0.95)
+    }
+}
+
+pub fn contact_threshold(batter: &BattingStats, pitcher: &PitchingStats, ballpark: &Ballpark, is_strike: bool) -> f64 {
+    let combined_batting = (batter.divinity + batter.musclitude + batter.thwackability - batter.patheticism) / 2.;
+    let ballpark_sum = (ballpark.forwardness - 0.5) + 0.5 * (ballpark.obtuseness - 0.5);
+    if is_strike {
+        clamp(0.78 - 0.08 * pitcher.ruthlessness + 0.16 * combined_batting - 0.1 * ballpark_sum, 0.6, 0.9)
+    } else {
+        clamp(0.4 - 0.1 * pitcher.ruthlessness + 0.35 * combined_batting - 0.1 * ballpark_sum, 0.1, 1.)
+    }
+}
+
+pub fn foul_threshold(batter: &BattingStats, ballpark: &Ballpark) -> f64 {
+    let batter_sum = (batter.musclitude + batter.thwackability + batter.divinity) / 3.;
+    0.25 + 0.1 * (ballpark.forwardness - 0.5) - 0.1 * (ballpark.obtuseness - 0.5) + 0.1 * batter_sum
+}
+
+pub fn out_threshold(batter: &BattingStats, pitcher: &PitchingStats, fielder: &DefenseStats, ballpark: &Ballpark) -> f64 {
+    let ballpark_sum = (ballpark.grandiosity - 0.5) + 0.5 * (ballpark.ominousness - 0.5) - 0.5 * (ballpark.inconvenience - 0.5);
+    0.311 + 0.1 * pitcher.unthwackability + 0.08 * fielder.omniscience - 0.1 * batter.thwackability
+        - 0.05 * ballpark_sum
+}
+
+// Rough starting points for fielded outs, like the pitch formulas above
+pub fn fly_threshold(batter: &BattingStats, pitcher: &PitchingStats) -> f64 {
+    // Buoyant batters get it in the air
+    clamp(0.18 + 0.3 * batter.buoyancy - 0.16 * pitcher.suppression, 0.01, 0.99)
+}
+
+pub fn advance_on_out_threshold(runner: &BaserunningStats, fielder: &DefenseStats) -> f64 {
+    clamp(0.1 + 0.3 * runner.indulgence + 0.1 * runner.continuation - 0.15 * fielder.tenaciousness, 0., 1.)
+}
+
+pub fn double_play_threshold(batter: &BattingStats, fielder: &DefenseStats) -> f64 {
+    clamp(0.35 + 0.2 * fielder.omniscience - 0.1 * batter.musclitude, 0., 1.)
+}
+
+pub fn fielders_choice_threshold(batter: &BattingStats, fielder: &DefenseStats) -> f64 {
+    clamp(0.5 + 0.1 * fielder.anticapitalism - 0.1 * batter.moxie, 0., 1.)
+}
+
+pub fn home_run_threshold(batter: &BattingStats, pitcher: &PitchingStats, ballpark: &Ballpark) -> f64 {
+    // Grandiosity is the big one here, the other attributes are shared evenly
+    let ballpark_sum = (0.4 * (ballpark.grandiosity - 0.5) +
+        0.2 * (ballpark.fortification - 0.5) +
+        0.2 * (ballpark.viscosity - 0.5) +
+        0.2 * (ballpark.ominousness - 0.5) +
+        0.2 * (ballpark.forwardness - 0.5)) / 1.2;
+    let pitcher_sum = (10. * pitcher.overpowerment + pitcher.suppression) / 11.;
+    0.12 + 0.16 * batter.divinity - 0.08 * pitcher_sum - 0.18 * ballpark_sum
+}
+
+pub fn triple_threshold(batter: &BaserunningStats, pitcher: &PitchingStats, fielder: &DefenseStats, ballpark: &Ballpark) -> f64 {
+    let ballpark_sum = (ballpark.grandiosity - 0.5) + 0.5 * (ballpark.fortification - 0.5) +
+        0.5 * (ballpark.elongation - 0.5);
+    0.045 + 0.2 * batter.ground_friction - 0.05 * pitcher.overpowerment - 0.02 * fielder.chasiness
+        + 0.034 * ballpark_sum
+}
+
+pub fn double_threshold(batter: &BattingStats, pitcher: &PitchingStats, fielder: &DefenseStats, ballpark: &Ballpark) -> f64 {
+    let ballpark_sum = (ballpark.forwardness - 0.5) + 0.5 * (ballpark.elongation - 0.5) +
+        0.5 * (ballpark.obtuseness - 0.5);
+    0.165 + 0.2 * batter.musclitude - 0.04 * pitcher.overpowerment - 0.009 * fielder.chasiness
+        + 0.027 * ballpark_sum
+}
+
+pub fn extra_base_threshold(runner: &BaserunningStats, fielder: &DefenseStats) -> f64 {
+    clamp(0.2 * runner.base_thirst + 0.1 * runner.continuation - 0.15 * fielder.tenaciousness, 0., 1.)
+}
+
+pub fn steal_attempt_threshold(runner: &BaserunningStats, fielder: &DefenseStats) -> f64 {
+    clamp(0.035 + 0.2 * runner.base_thirst - 0.05 * fielder.watchfulness, 0.001, 1.)
+}
+
+pub fn steal_success_threshold(runner: &BaserunningStats, fielder: &DefenseStats) -> f64 {
+    clamp(0.7 + 0.2 * runner.laserlikeness - 0.2 * fielder.tenaciousness - 0.1 * fielder.anticapitalism, 0., 1.)
+}
+
+// A more fortified stadium is harder to burn down
+pub fn eclipse_threshold(season: i32, ballpark: &Ballpark) -> f64 {
+    if season < 13 {
+        0.00045 - 0.0004 * ballpark.fortification
+    } else {
+        // (s14+) TODO This is a guess from a handful of incinerations. Needs a proper fit
+        0.00035 - 0.0003 * ballpark.fortification
+    }
+}
+
+pub fn supernova_eclipse_threshold(ballpark: &Ballpark) -> f64 {
+    // TODO Figure out the correct threshold for supernova eclipse. Fortification is assumed to
+    //  work the same way it does for regular eclipses
+    0.00025 - 0.0002 * ballpark.fortification
+}
+
+// Unstable players get their own, much higher, chance at incineration on top of the regular one
+pub fn unstable_incineration_threshold(ballpark: &Ballpark) -> f64 {
+    // TODO Figure out the correct threshold for unstable incinerations
+    0.002 - 0.001 * ballpark.fortification
+}
+
+pub fn feedback_threshold(flickering: bool) -> f64 {
+    // TODO Figure out the correct thresholds for feedback, including whether the stadium affects
+    //  them. Flickering players are known to be swapped far more often, but not exactly how much
+    //  more
+    if flickering { 0.055 } else { 0.0001 }
+}
+
+pub fn blooddrain_threshold() -> f64 {
+    // TODO Figure out the correct threshold for blooddrain, including whether the stadium affects it
+    0.00065
+}
+
+// Siphons get their own roll every pitch they're involved in
+pub fn siphon_threshold() -> f64 {
+    // TODO Figure out the correct threshold for siphons
+    0.0025
+}
+
+// More birds in the stadium means more bird events. TODO Figure out the correct thresholds for
+//  birds; these are fit to flavor text counts
+pub fn birds_threshold(stadium_birds: i32) -> f64 {
+    0.012 + 0.00001 * stadium_birds as f64
+}
+
+pub fn unshell_threshold(stadium_birds: i32) -> f64 {
+    0.00015 + 0.000001 * stadium_birds as f64
+}
+
+pub fn reverb_threshold() -> f64 {
+    // TODO Figure out the correct threshold for reverb, including whether the stadium affects it
+    0.00021
+}
+
+pub fn flood_threshold(ballpark: &Ballpark) -> f64 {
+    // TODO Figure out the correct threshold for flooding
+    0.055 - 0.05 * ballpark.fortification
+}
+
+pub fn salmon_threshold(ballpark: &Ballpark) -> f64 {
+    // TODO Figure out the correct threshold for salmon
+    0.0875 - 0.05 * ballpark.fortification
+}
+
+pub fn night_threshold() -> f64 {
+    // TODO Figure out the correct threshold for night
+    0.01
+}
@@ -1,14 +1,21 @@
 use anyhow::anyhow;
-use fed::{FedEventData, GameEvent, GamePitch, SubEvent, TogglePerforming, Weather};
-use phf::phf_map;
+use fed::{FedEventData, GameEvent, GamePitch, ItemDamageEvent, Scores, ScoringPlayer, SpicyStatus, SubEvent, TogglePerforming, Weather};
 use uuid::Uuid;
-use crate::chronicler_schema::{Player, Team};
+use crate::chronicler_schema::{Player, Stadium, Team};
+use itertools::Itertools;
+use crate::formulas::{self, Ballpark};
+use crate::mods::{Mod, ModLayer};
 use crate::rng::Rng;
 use crate::sim::World;
+use crate::stats::{self, BattingStats, DefenseStats, PitchingStats, StatContext};
 
-static ITEM_NAMES: phf::Map<&'static str, &'static str> = phf_map! {
-    "AN_ACTUAL_AIRPLANE" => "An Actual Airplane",
-};
+mod weather;
+
+// TODO Figure out the correct threshold for item damage
+const ITEM_DAMAGE_THRESHOLD: f64 = 0.0002;
+// TODO Figure out the correct thresholds for ballpark mods
+const PEANUT_MISTER_THRESHOLD: f64 = 0.0005;
+const SMITHY_THRESHOLD: f64 = 0.0004;
 
 #[derive(Debug)]
 pub enum GamePhase {
@@ -25,6 +32,7 @@ pub enum GamePhase {
 pub struct GameByTeam {
     pub team_id: Uuid,
     pub team_batter_count: i64,
+    pub score: f64,
 }
 
 impl GameByTeam {
@@ -32,10 +40,19 @@ impl GameByTeam {
         Self {
             team_id,
             team_batter_count: -1,
+            score: 0.,
         }
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct Baserunner {
+    pub id: Uuid,
+    pub name: String,
+    // Zero-indexed, so a runner on first is on base 0
+    pub base: i32,
+}
+
 #[derive(Debug)]
 pub struct Game {
     game_id: Uuid,
@@ -43,27 +60,51 @@ pub struct Game {
     away: GameByTeam,
     weather: Weather,
     stadium_id: Option<Uuid>,
+    season: i32,
+    day: i32,
 
     phase: GamePhase,
     play: i64,
     top_of_inning: bool,
     inning: i32,
+    balls: i32,
+    strikes: i32,
+    outs: i32,
+    // Kept sorted with the lead runner first
+    baserunners: Vec<Baserunner>,
+    // (away, home) scores when the current inning started, so salmon know what to take back
+    inning_start_scores: (f64, f64),
+    // Whether the first half-inning has started. Salmon can take the game back before the first
+    // inning, so `inning` can't tell us this.
+    started: bool,
+    // Set when salmon have already swum during this break between innings, so the half-inning
+    // start that follows doesn't roll for them again
+    salmon_resolved: bool,
 }
 
 impl Game {
-    pub fn new(game_event: &GameEvent, weather: Weather, stadium_id: Option<Uuid>) -> Self {
+    pub fn new(game_event: &GameEvent, weather: Weather, stadium_id: Option<Uuid>, season: i32, day: i32) -> Self {
         Game {
             game_id: game_event.game_id,
             home: GameByTeam::new(game_event.home_team),
             away: GameByTeam::new(game_event.away_team),
             weather,
             stadium_id,
+            season,
+            day,
             phase: GamePhase::NotStarted,
             play: 0,
             // play starts at the "bottom of the 0th" so that the first half-inning-start moves us
             // to the top of the first. innings are zero-indexed so the "zeroth" is -1
             top_of_inning: false,
             inning: -1,
+            balls: 0,
+            strikes: 0,
+            outs: 0,
+            baserunners: Vec::new(),
+            inning_start_scores: (0., 0.),
+            started: false,
+            salmon_resolved: false,
         }
     }
 
@@ -89,7 +130,7 @@ impl Game {
                 Ok(self.play_ball())
             }
             GamePhase::StartOfHalfInning => {
-                self.start_half_inning(world)
+                self.start_half_inning(world, rng)
             }
             GamePhase::SuperyummyAnnouncement => {
                 self.superyummy_announcement(world)
@@ -119,17 +160,30 @@ impl Game {
         }
     }
 
-    fn start_half_inning(&mut self, world: &mut World) -> anyhow::Result<FedEventData> {
-        if self.inning < 0 && (
-            world.any_player_on_team_has_mod(self.batting_team_game_data().team_id, "SUPERYUMMY")? ||
-                world.any_player_on_team_has_mod(self.pitching_team_game_data().team_id, "SUPERYUMMY")?
+    fn start_half_inning(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<FedEventData> {
+        // Salmon come between full innings, so only before a top half, and only once per break
+        if self.started && !self.top_of_inning && !self.salmon_resolved {
+            if let Some(salmon) = self.roll_salmon(world, rng)? {
+                self.salmon_resolved = true;
+                return Ok(salmon);
+            }
+        }
+        self.salmon_resolved = false;
+
+        if !self.started && (
+            world.any_player_on_team_has_mod(self.batting_team_game_data().team_id, Mod::Superyummy, self.stadium_id)? ||
+                world.any_player_on_team_has_mod(self.pitching_team_game_data().team_id, Mod::Superyummy, self.stadium_id)?
         ) {
             self.phase = GamePhase::SuperyummyAnnouncement;
         } else {
             self.phase = GamePhase::BatterUp;
         }
+        self.started = true;
         self.top_of_inning = !self.top_of_inning;
         self.inning += 1;
+        if self.top_of_inning {
+            self.inning_start_scores = (self.away.score, self.home.score);
+        }
         Ok(FedEventData::HalfInningStart {
             game: self.game_event(),
             top_of_inning: self.top_of_inning,
@@ -161,28 +215,71 @@ impl Game {
         if self.top_of_inning { &mut self.away } else { &mut self.home }
     }
 
+    // The number of bases a runner has to touch to score, counting home
+    fn num_bases(&self, world: &World) -> anyhow::Result<i32> {
+        let team = self.batting_team(world)
+            .ok_or_else(|| anyhow!("Couldn't find batting team"))?;
+        Ok(if team.has_mod(Mod::FifthBase) { 5 } else { 4 })
+    }
+
+    fn stat_context(&self) -> StatContext {
+        StatContext {
+            season: self.season,
+            day: self.day,
+            weather: self.weather,
+            home_team: self.home.team_id,
+            stadium_id: self.stadium_id,
+        }
+    }
+
+    fn stadium<'a>(&self, world: &'a World) -> Option<&'a Stadium> {
+        world.stadiums.get(&self.stadium_id?)
+    }
+
+    fn ballpark(&self, world: &World) -> Ballpark {
+        // Games without a stadium are played on a perfectly average field
+        self.stadium(world).map_or(Ballpark::NEUTRAL, Ballpark::from)
+    }
+
     fn superyummy_announcement(&mut self, world: &mut World) -> anyhow::Result<FedEventData> {
         self.phase = GamePhase::BatterUp;
 
-        let (player, team_id) = (|| {
+        let (player_id, team_id) = (|| {
             for team_id in [self.batting_team_game_data().team_id, self.pitching_team_game_data().team_id] {
                 for player in world.players_on_team(team_id)
                     .ok_or_else(|| anyhow!("Couldn't find batting/pitching team"))? {
                     let player = player.ok_or_else(|| anyhow!("Couldn't find player from team rotation or lineup"))?;
-                    if player.has_mod("SUPERYUMMY") { return Ok((player, team_id)); }
+                    if world.player_has_mod(player, Mod::Superyummy, self.stadium_id) { return Ok((player.id, team_id)); }
                 }
             }
             Err(anyhow!("Got to state SuperyummyAnnouncement, but no players in this game are Superyummy"))
         })()?;
 
+        // Superyummy players overperform in peanuts weather and underperform in anything else. The
+        // mod sticks around until it's flipped, so it's only news the first time in a row.
+        let is_overperforming = self.weather == Weather::Peanuts;
+        let (new_mod, old_mod) = if is_overperforming {
+            (Mod::Overperforming, Mod::Underperforming)
+        } else {
+            (Mod::Underperforming, Mod::Overperforming)
+        };
+        let player = world.players.get(&player_id)
+            .ok_or_else(|| anyhow!("Couldn't find Superyummy player"))?;
+        let is_first_proc = !world.player_has_mod(player, new_mod.clone(), self.stadium_id);
+        let player = world.players.get_mut(&player_id)
+            .ok_or_else(|| anyhow!("Couldn't find Superyummy player"))?;
+        player.remove_mod(&old_mod);
+        player.add_mod(ModLayer::Perm, new_mod);
+        let player_name = player.name.clone();
+
         Ok(FedEventData::SuperyummyGameStart {
             game: self.game_event(),
             toggle: TogglePerforming {
-                player_id: player.id,
+                player_id,
                 team_id,
-                player_name: player.name.to_owned(),
-                is_overperforming: false, // TODO this should look at the weather
-                is_first_proc: true, // TODO This should read whether the player has over/underperforming already
+                player_name,
+                is_overperforming,
+                is_first_proc,
                 sub_event: SubEvent::nil(),
             },
         })
@@ -208,7 +305,8 @@ impl Game {
     fn get_pitcher_and_team<'a>(&self, world: &'a World) -> anyhow::Result<(&'a Player, &'a Team)> {
         let team = self.pitching_team(world)
             .ok_or_else(|| anyhow!("Couldn't find pitching team"))?;
-        let pitcher_id = team.lineup[(team.rotation_slot as usize) % team.lineup.len()];
+        let pitcher_id = team.active_pitcher()
+            .ok_or_else(|| anyhow!("Pitching team has an empty rotation"))?;
         let pitcher = world.players.get(&pitcher_id)
             .ok_or_else(|| anyhow!("Couldn't find pitcher"))?;
         Ok((pitcher, team))
@@ -222,15 +320,7 @@ impl Game {
             game: self.game_event(),
             batter_name: batter.name.to_owned(),
             team_nickname: team.nickname.to_owned(),
-            wielding_item: batter.bat.as_ref().map(|bat| {
-                Ok::<_, anyhow::Error>(if !bat.is_empty() {
-                    Some(ITEM_NAMES.get(bat)
-                        .ok_or_else(|| anyhow!("Unknown item name. Note: Item names are in a hard-coded list, maybe it needs to be added?"))?
-                        .to_string())
-                } else {
-                    None
-                })
-            }).transpose()?.flatten(),
+            wielding_item: batter.wielding_item_name(),
             inhabiting: None, // TODO handle ghosts
             is_repeating: false, // TODO
         })
@@ -248,15 +338,15 @@ impl Game {
 
         // TODO (s?) elsewhere/scattered
 
-        if let Some(weather) = self.roll_weather(rng)? { return Ok(weather); }
+        if let Some(weather) = self.roll_weather(world, rng)? { return Ok(weather); }
 
         // TODO parties
-        // TODO flooding
+        if let Some(flood) = self.roll_flooding(world, rng)? { return Ok(flood); }
         // TODO polarity
         // TODO consumers
-        // TODO ballpark effects
+        if let Some(effect) = self.roll_ballpark_effects(world, rng)? { return Ok(effect); }
 
-        // TODO base stealing
+        if let Some(steal) = self.roll_steals(world, rng)? { return Ok(steal); }
 
         // TODO electric
         // TODO debt
@@ -267,154 +357,533 @@ impl Game {
         self.actual_pitch(world, rng)
     }
 
+    fn roll_ballpark_effects(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        let Some(stadium) = self.stadium(world) else { return Ok(None) };
+        let has_peanut_mister = stadium.has_mod(Mod::PeanutMister);
+        let has_smithy = stadium.has_mod(Mod::Smithy);
+
+        if has_peanut_mister && rng.next() < PEANUT_MISTER_THRESHOLD {
+            // The mister picks a player from the batting team and cures their allergy, whether or
+            // not they had one
+            let player = self.roll_batting_team_player(world, rng)?;
+            player.peanut_allergy = Some(false);
+            let (player_id, player_name) = (player.id, player.name.clone());
+            return Ok(Some(FedEventData::PeanutMister {
+                game: self.game_event(),
+                player_id,
+                player_name,
+                sub_event: SubEvent::nil(),
+            }));
+        }
+
+        if has_smithy && rng.next() < SMITHY_THRESHOLD {
+            let player = self.roll_batting_team_player(world, rng)?;
+            // The Smithy fixes the most damaged item
+            let Some(item) = player.items.iter_mut().flatten()
+                .filter(|item| item.durability >= 0)
+                .min_by_key(|item| item.health) else { return Ok(None) };
+            item.health = item.durability;
+            let (item_id, item_name) = (item.id, item.name.clone());
+            let player_name = player.name.clone();
+            return Ok(Some(FedEventData::Smithy {
+                game: self.game_event(),
+                player_name,
+                item_id,
+                item_name,
+                sub_event: SubEvent::nil(),
+            }));
+        }
+
+        Ok(None)
+    }
+
+    fn roll_batting_team_player<'a>(&self, world: &'a mut World, rng: &mut Rng) -> anyhow::Result<&'a mut Player> {
+        let team = self.batting_team(world)
+            .ok_or_else(|| anyhow!("Couldn't find batting team"))?;
+        let player_id = team.lineup[(rng.next() * team.lineup.len() as f64) as usize];
+        world.players.get_mut(&player_id)
+            .ok_or_else(|| anyhow!("Couldn't find player from batting team lineup"))
+    }
+
     fn actual_pitch(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<FedEventData> {
         // This is when we've passed all the things that can preempt a pitch and we finally know
         // one actually gets thrown
         let roll = rng.next();
-        let batter = self.get_batter(world)?;
-        let pitcher = self.get_pitcher(world)?;
+        let ctx = self.stat_context();
+        let batter_id = self.get_batter(world)?.id;
+        let pitcher_id = self.get_pitcher(world)?.id;
+        let batter = stats::batting(world, self.get_batter(world)?, &ctx);
+        let pitcher = stats::pitching(world, self.get_pitcher(world)?, &ctx);
+        let ballpark = self.ballpark(world);
 
-        // NOT EVEN CLOSE TO ACCURATE YET. I just want something that runs
-        let is_strike = roll < 0.2 + 0.35 * pitcher.ruthlessness + 0.1 * batter.musclitude;
+        let is_strike = roll < formulas::strike_threshold(&batter, &pitcher, &ballpark);
 
         // TODO acidic pitch
         // TODO firey
 
         // TODO flinch
 
-        // Again: not even close to correct yet
-        let swung_threshold = if is_strike {
-            let combined_batting = (batter.divinity + batter.musclitude + (1. - batter.patheticism) + batter.thwackability) / 4.;
-            0.7 + 0.35 * combined_batting - 0.4 * pitcher.ruthlessness
-        } else {
-            let mut threshold = (12. * pitcher.ruthlessness - 5. * batter.moxie + 5. * batter.patheticism) / 20.;
-            // Can't "just" use max(min(... because rust cares that NaN isn't totally ordered
-            if threshold > 0.95 { threshold = 0.95 }
-            if threshold < 0.1 { threshold = 0.1 }
-            threshold
-        };
-        let swung = rng.next() < swung_threshold;
+        let swung = rng.next() < formulas::swing_threshold(&batter, &pitcher, is_strike);
 
+        let batter_name = self.get_batter(world)?.name.clone();
         if !swung {
             if is_strike {
+                let struck_out = self.add_strike(world)?;
+                let pitcher_item_damage = self.roll_item_damage(world, rng, pitcher_id)?;
+                if struck_out {
+                    return Ok(FedEventData::StrikeoutLooking {
+                        game: self.game_event(),
+                        batter_name,
+                        pitcher_item_damage,
+                    });
+                }
                 return Ok(FedEventData::StrikeLooking {
                     game: self.game_event(),
                     pitch: GamePitch { double_strike: None },
-                    balls: 0,
-                    strikes: 1,
-                    pitcher_item_damage: None,
+                    balls: self.balls,
+                    strikes: self.strikes,
+                    pitcher_item_damage,
                 });
             } else {
+                let walk = self.add_ball(world)?;
+                let batter_item_damage = self.roll_item_damage(world, rng, batter_id)?;
+                if let Some(scores) = walk {
+                    return Ok(FedEventData::Walk {
+                        game: self.game_event(),
+                        batter_id,
+                        batter_name,
+                        scores,
+                        batter_item_damage,
+                    });
+                }
                 return Ok(FedEventData::Ball {
                     game: self.game_event(),
-                    balls: 1,
-                    strikes: 0,
-                    batter_item_damage: None,
+                    balls: self.balls,
+                    strikes: self.strikes,
+                    batter_item_damage,
+                });
+            }
+        }
+
+        let made_contact = rng.next() < formulas::contact_threshold(&batter, &pitcher, &ballpark, is_strike);
+        if !made_contact {
+            let struck_out = self.add_strike(world)?;
+            let pitcher_item_damage = self.roll_item_damage(world, rng, pitcher_id)?;
+            if struck_out {
+                return Ok(FedEventData::StrikeoutSwinging {
+                    game: self.game_event(),
+                    batter_name,
+                    pitcher_item_damage,
                 });
             }
+            return Ok(FedEventData::StrikeSwinging {
+                game: self.game_event(),
+                balls: self.balls,
+                strikes: self.strikes,
+                pitcher_item_damage,
+            });
+        }
+
+        let is_foul = rng.next() < formulas::foul_threshold(&batter, &ballpark);
+        if is_foul {
+            // Fouls can't be the third strike
+            if self.strikes < self.max_strikes(world)? - 1 { self.strikes += 1; }
+            return Ok(FedEventData::FoulBall {
+                game: self.game_event(),
+                balls: self.balls,
+                strikes: self.strikes,
+            });
         }
 
-        todo!()
+        let out_roll = rng.next();
+        let out_fielder = self.roll_fielder(world, rng)?;
+        let (out_fielder_id, out_fielder_name) = (out_fielder.id, out_fielder.name.clone());
+        let out_fielder = stats::defense(world, out_fielder, &ctx);
+        if out_roll < formulas::out_threshold(&batter, &pitcher, &out_fielder, &ballpark) {
+            return self.fielded_out(world, rng, &batter, &pitcher, (out_fielder_id, out_fielder_name), &out_fielder);
+        }
+
+        self.hit(world, rng)
     }
 
-    fn roll_weather(&self, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
-        match self.weather {
-            Weather::Sun2 => { Ok(None) }
-            Weather::Snowy => { todo!() }
-            Weather::SolarEclipse => {
-                if rng.next() < 0.00025 {
-                    todo!()
-                } else {
-                    Ok(None)
-                }
-            }
-            Weather::Glitter => { todo!() }
-            Weather::Blooddrain => {
-                // TODO Figure out the correct threshold for blooddrain
-                if rng.next() < 0.00025 {
-                    todo!()
-                } else {
-                    Ok(None)
-                }
-            }
-            Weather::Peanuts => {
-                // TODO Figure out the correct threshold for peanuts
-                if rng.next() < 0.00025 {
-                    todo!()
-                } else {
-                    Ok(None)
-                }
+    // How many strikes strike the batter out, and how many balls walk them
+    fn max_strikes(&self, world: &World) -> anyhow::Result<i32> {
+        let team = self.batting_team(world)
+            .ok_or_else(|| anyhow!("Couldn't find batting team"))?;
+        Ok(if team.has_mod(Mod::FourthStrike) { 4 } else { 3 })
+    }
+
+    fn max_balls(&self, world: &World) -> anyhow::Result<i32> {
+        let team = self.batting_team(world)
+            .ok_or_else(|| anyhow!("Couldn't find batting team"))?;
+        Ok(if team.has_mod(Mod::WalkInThePark) { 3 } else { 4 })
+    }
+
+    // Adds a strike to the count. Returns whether that struck the batter out.
+    fn add_strike(&mut self, world: &World) -> anyhow::Result<bool> {
+        self.strikes += 1;
+        if self.strikes < self.max_strikes(world)? { return Ok(false); }
+        self.end_plate_appearance();
+        self.add_out();
+        Ok(true)
+    }
+
+    // Adds a ball to the count. If that walks the batter, returns who scored on the walk.
+    fn add_ball(&mut self, world: &World) -> anyhow::Result<Option<Scores>> {
+        self.balls += 1;
+        if self.balls < self.max_balls(world)? { return Ok(None); }
+
+        let batter = self.get_batter(world)?;
+        let batter_runner = Baserunner { id: batter.id, name: batter.name.clone(), base: 0 };
+        let scored = self.force_runners_ahead(world)?;
+        self.baserunners.push(batter_runner);
+        let scores = self.score_runners(scored);
+        self.end_plate_appearance();
+        Ok(Some(scores))
+    }
+
+    fn add_out(&mut self) {
+        self.outs += 1;
+        if self.outs >= 3 {
+            self.end_half_inning();
+        }
+    }
+
+    // Moves up everyone who has to make room for the batter taking first, and returns whoever that
+    // pushes home. A runner is only forced if every base behind them is full.
+    fn force_runners_ahead(&mut self, world: &World) -> anyhow::Result<Vec<Baserunner>> {
+        let home = self.num_bases(world)? - 1;
+        // Runners are sorted lead first, so start from the back
+        let mut forced_base = 0;
+        for runner in self.baserunners.iter_mut().rev() {
+            if runner.base != forced_base { break; }
+            runner.base += 1;
+            forced_base += 1;
+        }
+        let (scored, stayed): (Vec<_>, Vec<_>) = std::mem::take(&mut self.baserunners).into_iter()
+            .partition(|runner| runner.base >= home);
+        self.baserunners = stayed;
+        Ok(scored)
+    }
+
+    // The ball's caught or fielded. Fly outs let runners tag up, and ground outs can turn into a
+    // double play or a fielder's choice when there's a runner on first.
+    fn fielded_out(&mut self, world: &mut World, rng: &mut Rng, batter: &BattingStats, pitcher: &PitchingStats, (fielder_id, fielder_name): (Uuid, String), fielder: &DefenseStats) -> anyhow::Result<FedEventData> {
+        let batter_name = self.get_batter(world)?.name.clone();
+        // Nobody gets to move up on the third out
+        let is_third_out = self.outs >= 2;
+
+        if rng.next() < formulas::fly_threshold(batter, pitcher) {
+            let scored = if is_third_out { Vec::new() } else { self.advance_on_out(world, rng, fielder)? };
+            let scores = self.score_runners(scored);
+            self.end_plate_appearance();
+            self.add_out();
+            let fielder_item_damage = self.roll_item_damage(world, rng, fielder_id)?;
+            return Ok(FedEventData::FlyOut {
+                game: self.game_event(),
+                batter_name,
+                fielder_name,
+                scores,
+                fielder_item_damage,
+            });
+        }
+
+        let runner_on_first = self.baserunners.last().is_some_and(|runner| runner.base == 0);
+        if runner_on_first && !is_third_out {
+            if rng.next() < formulas::double_play_threshold(batter, fielder) {
+                // The runner from first and the batter are both out, and everyone else holds
+                self.baserunners.pop();
+                self.end_plate_appearance();
+                self.outs += 1;
+                self.add_out();
+                let fielder_item_damage = self.roll_item_damage(world, rng, fielder_id)?;
+                return Ok(FedEventData::DoublePlay {
+                    game: self.game_event(),
+                    batter_name,
+                    scores: self.score_runners(Vec::new()),
+                    fielder_item_damage,
+                });
             }
-            Weather::Birds => {
-                // TODO Figure out the correct threshold for birds
-                if rng.next() < 0.00025 {
-                    todo!()
-                } else {
-                    Ok(None)
-                }
+
+            if rng.next() < formulas::fielders_choice_threshold(batter, fielder) {
+                // The fielders take the force at second, so the runner from first is out there
+                // instead of the batter
+                let scored = self.force_runners_ahead(world)?;
+                let runner_out = self.baserunners.pop()
+                    .ok_or_else(|| anyhow!("Fielder's choice with nobody forced to second"))?;
+                let batter = self.get_batter(world)?;
+                self.baserunners.push(Baserunner { id: batter.id, name: batter.name.clone(), base: 0 });
+                let scores = self.score_runners(scored);
+                self.end_plate_appearance();
+                self.add_out();
+                let fielder_item_damage = self.roll_item_damage(world, rng, fielder_id)?;
+                return Ok(FedEventData::FieldersChoice {
+                    game: self.game_event(),
+                    batter_name,
+                    runner_out_name: runner_out.name,
+                    scores,
+                    fielder_item_damage,
+                });
             }
-            Weather::Feedback => {
-                // TODO Figure out the correct threshold for feedback
-                if rng.next() < 0.00025 {
-                    todo!()
-                } else {
-                    Ok(None)
-                }
+        }
+
+        let scored = if is_third_out { Vec::new() } else { self.advance_on_out(world, rng, fielder)? };
+        let scores = self.score_runners(scored);
+        self.end_plate_appearance();
+        self.add_out();
+        let fielder_item_damage = self.roll_item_damage(world, rng, fielder_id)?;
+        Ok(FedEventData::GroundOut {
+            game: self.game_event(),
+            batter_name,
+            fielder_name,
+            scores,
+            fielder_item_damage,
+        })
+    }
+
+    // Each runner gets a chance to take the next base on an out, if it's open. Lead runners go
+    // first so they can make room for the ones behind them. Returns whoever made it home.
+    fn advance_on_out(&mut self, world: &World, rng: &mut Rng, fielder: &DefenseStats) -> anyhow::Result<Vec<Baserunner>> {
+        let ctx = self.stat_context();
+        let home = self.num_bases(world)? - 1;
+        let mut scored = Vec::new();
+        let mut advanced: Vec<Baserunner> = Vec::new();
+        for mut runner in std::mem::take(&mut self.baserunners) {
+            let runner_player = world.players.get(&runner.id)
+                .ok_or_else(|| anyhow!("Couldn't find baserunner"))?;
+            let running = stats::baserunning(world, runner_player, &ctx);
+            let base_ahead_open = advanced.last().is_none_or(|ahead| ahead.base > runner.base + 1);
+            if base_ahead_open && rng.next() < formulas::advance_on_out_threshold(&running, fielder) {
+                runner.base += 1;
             }
-            Weather::Reverb => {
-                // TODO Figure out the correct threshold for reverb
-                if rng.next() < 0.00025 {
-                    todo!()
-                } else {
-                    Ok(None)
-                }
+            if runner.base >= home {
+                scored.push(runner);
+            } else {
+                advanced.push(runner);
             }
-            Weather::BlackHole => { Ok(None) }
-            Weather::Coffee => { Ok(None) }
-            Weather::Coffee2 => { Ok(None) }
-            Weather::Coffee3s => { Ok(None) }
-            Weather::Flooding => {
-                // TODO Figure out the correct threshold for flooding
-                if rng.next() < 0.00025 {
-                    todo!()
-                } else {
-                    Ok(None)
+        }
+        self.baserunners = advanced;
+        Ok(scored)
+    }
+
+    // (s15+) Every outcome except a foul ball gives someone a chance to damage one of their items.
+    // It's whoever came out ahead on the pitch: the pitcher on strikes, the batter on balls and
+    // hits, and the fielder on outs.
+    fn roll_item_damage(&self, world: &mut World, rng: &mut Rng, player_id: Uuid) -> anyhow::Result<Option<ItemDamageEvent>> {
+        if self.season < 14 { return Ok(None); }
+
+        let player = world.players.get_mut(&player_id)
+            .ok_or_else(|| anyhow!("Couldn't find player to damage items for"))?;
+        // Unbreakable and already-broken items can't be damaged, and if there's nothing that can be
+        // damaged there's no roll
+        let breakable = player.items().iter()
+            .positions(|item| item.durability >= 0 && !item.is_broken())
+            .collect::<Vec<_>>();
+        if breakable.is_empty() { return Ok(None); }
+
+        if rng.next() >= ITEM_DAMAGE_THRESHOLD { return Ok(None); }
+        let index = breakable[(rng.next() * breakable.len() as f64) as usize];
+        let item = player.damage_item(index)?;
+        let (item_id, item_name, health) = (item.id, item.name.clone(), item.health);
+
+        Ok(Some(ItemDamageEvent {
+            item_id,
+            item_name,
+            item_health_after: health,
+            player_name: player.name.clone(),
+            sub_event: SubEvent::nil(),
+        }))
+    }
+
+    fn roll_steals(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        let home = self.num_bases(world)? - 1;
+        let ctx = self.stat_context();
+        // Lead runner gets the first chance. A runner can only try for a base nobody's standing on,
+        // which includes stealing home
+        for i in 0..self.baserunners.len() {
+            let base_ahead = self.baserunners[i].base + 1;
+            if i > 0 && self.baserunners[i - 1].base == base_ahead { continue; }
+
+            let runner = world.players.get(&self.baserunners[i].id)
+                .ok_or_else(|| anyhow!("Couldn't find baserunner"))?;
+            let runner = stats::baserunning(world, runner, &ctx);
+            let fielder = stats::defense(world, self.roll_fielder(world, rng)?, &ctx);
+            if rng.next() >= formulas::steal_attempt_threshold(&runner, &fielder) { continue; }
+
+            let success = rng.next() < formulas::steal_success_threshold(&runner, &fielder);
+            let runner = self.baserunners.remove(i);
+            // base_stolen is one-indexed, so stealing second is base 2
+            let base_stolen = base_ahead + 1;
+
+            if !success {
+                self.outs += 1;
+                if self.outs >= 3 {
+                    // The batter didn't finish their plate appearance, so they lead off next time
+                    self.batting_team_game_data_mut().team_batter_count -= 1;
+                    self.end_half_inning();
                 }
+                return Ok(Some(FedEventData::CaughtStealing {
+                    game: self.game_event(),
+                    runner_name: runner.name,
+                    base_stolen,
+                }));
             }
-            Weather::Salmon => {
-                // TODO Figure out the correct threshold for salmon
-                if rng.next() < 0.00025 {
-                    todo!()
-                } else {
-                    Ok(None)
-                }
+
+            let runner_player = world.players.get(&runner.id)
+                .ok_or_else(|| anyhow!("Couldn't find baserunner"))?;
+            let blaserunning = world.player_has_mod(runner_player, Mod::Blaserunning, self.stadium_id);
+            if blaserunning {
+                self.batting_team_game_data_mut().score += 0.2;
             }
-            Weather::PolarityPlus => { Ok(None) }
-            Weather::PolarityMinus => { Ok(None) }
-            Weather::Sun90 => { Ok(None) }
-            Weather::SunPoint1 => { Ok(None) }
-            Weather::SumSun => { Ok(None) }
-            Weather::SupernovaEclipse => {
-                // TODO Figure out the correct threshold for supernova eclipse
-                if rng.next() < 0.00025 {
-                    todo!()
-                } else {
-                    Ok(None)
-                }
+            if base_ahead >= home {
+                self.batting_team_game_data_mut().score += 1.;
+            } else {
+                self.baserunners.insert(i, Baserunner { base: base_ahead, ..runner.clone() });
             }
-            Weather::BlackHoleBlackHole => { Ok(None) }
-            Weather::Jazz => { todo!() }
-            Weather::Night => {
-                // TODO Figure out the correct threshold for night
-                if rng.next() < 0.00025 {
-                    todo!()
-                } else {
-                    Ok(None)
+
+            return Ok(Some(FedEventData::StolenBase {
+                game: self.game_event(),
+                runner_name: runner.name,
+                runner_id: runner.id,
+                base_stolen,
+                free_refill: None,
+                blaserunning,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    fn end_half_inning(&mut self) {
+        self.outs = 0;
+        self.balls = 0;
+        self.strikes = 0;
+        self.baserunners.clear();
+        self.phase = GamePhase::StartOfHalfInning;
+    }
+
+    fn roll_fielder<'a>(&self, world: &'a World, rng: &mut Rng) -> anyhow::Result<&'a Player> {
+        let team = self.pitching_team(world)
+            .ok_or_else(|| anyhow!("Couldn't find pitching team"))?;
+        let fielder_id = team.lineup[(rng.next() * team.lineup.len() as f64) as usize];
+        world.players.get(&fielder_id)
+            .ok_or_else(|| anyhow!("Couldn't find fielder"))
+    }
+
+    fn hit(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<FedEventData> {
+        let ctx = self.stat_context();
+        let batter = self.get_batter(world)?;
+        let batting = stats::batting(world, batter, &ctx);
+        let pitcher = stats::pitching(world, self.get_pitcher(world)?, &ctx);
+        let ballpark = self.ballpark(world);
+
+        if rng.next() < formulas::home_run_threshold(&batting, &pitcher, &ballpark) {
+            return self.home_run(world, rng);
+        }
+
+        let fielder = stats::defense(world, self.roll_fielder(world, rng)?, &ctx);
+        let batter_running = stats::baserunning(world, batter, &ctx);
+        // Both rolls happen on every hit, even when the first one would settle it (see handle_hit in
+        // resim)
+        let double_roll = rng.next();
+        let triple_roll = rng.next();
+        let num_bases = if triple_roll < formulas::triple_threshold(&batter_running, &pitcher, &fielder, &ballpark) {
+            3
+        } else if double_roll < formulas::double_threshold(&batting, &pitcher, &fielder, &ballpark) {
+            2
+        } else {
+            1
+        };
+
+        // Everyone on base is forced ahead by the length of the hit, then gets a chance to take an
+        // extra base if the base in front of them is open. Lead runners go first so they can open
+        // up bases for the runners behind them.
+        let home = self.num_bases(world)? - 1;
+        let mut scored = Vec::new();
+        let mut advanced: Vec<Baserunner> = Vec::new();
+        for mut runner in std::mem::take(&mut self.baserunners) {
+            runner.base += num_bases;
+            if runner.base < home {
+                let runner_player = world.players.get(&runner.id)
+                    .ok_or_else(|| anyhow!("Couldn't find baserunner"))?;
+                let running = stats::baserunning(world, runner_player, &ctx);
+                let base_ahead_open = advanced.last().is_none_or(|ahead| ahead.base > runner.base + 1);
+                if base_ahead_open && rng.next() < formulas::extra_base_threshold(&running, &fielder) {
+                    runner.base += 1;
                 }
             }
-            _ => {
-                Err(anyhow!("Encountered weather {:?}, but it was never used", self.weather))
+            if runner.base >= home {
+                scored.push(runner);
+            } else {
+                advanced.push(runner);
             }
         }
+        advanced.push(Baserunner {
+            id: batter.id,
+            name: batter.name.clone(),
+            base: num_bases - 1,
+        });
+        self.baserunners = advanced;
+
+        let batter_id = batter.id;
+        let batter_name = batter.name.clone();
+        let scores = self.score_runners(scored);
+        self.end_plate_appearance();
+        let batter_item_damage = self.roll_item_damage(world, rng, batter_id)?;
+
+        Ok(FedEventData::Hit {
+            game: self.game_event(),
+            batter_id,
+            batter_name,
+            num_bases,
+            is_special: false,
+            scores,
+            spicy_status: SpicyStatus::None,
+            batter_item_damage,
+        })
+    }
+
+    fn home_run(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<FedEventData> {
+        let batter = self.get_batter(world)?;
+        let batter_id = batter.id;
+        let batter_name = batter.name.clone();
+
+        // Everyone on base comes home along with the batter
+        let num_runs = self.baserunners.len() as i32 + 1;
+        self.baserunners.clear();
+        self.batting_team_game_data_mut().score += num_runs as f64;
+        self.end_plate_appearance();
+        let batter_item_damage = self.roll_item_damage(world, rng, batter_id)?;
+
+        Ok(FedEventData::HomeRun {
+            game: self.game_event(),
+            magmatic: false,
+            batter_id,
+            batter_name,
+            num_runs,
+            free_refills: vec![],
+            spicy_status: SpicyStatus::None,
+            is_special: false,
+            batter_item_damage,
+        })
     }
-}
\ No newline at end of file
+
+    fn score_runners(&mut self, scored: Vec<Baserunner>) -> Scores {
+        self.batting_team_game_data_mut().score += scored.len() as f64;
+        Scores {
+            scores: scored.into_iter()
+                .map(|runner| ScoringPlayer {
+                    player_id: runner.id,
+                    player_name: runner.name,
+                })
+                .collect(),
+            free_refills: vec![],
+        }
+    }
+
+    fn end_plate_appearance(&mut self) {
+        self.balls = 0;
+        self.strikes = 0;
+        self.phase = GamePhase::BatterUp;
+    }
+}
@@ -0,0 +1,790 @@
+// Weather events. These live in their own module because there are a lot of them, but they're
+// part of the pitch like everything else in game.rs.
+use anyhow::anyhow;
+use fed::{BlooddrainCategory, FedEventData, FloodingSweptPlayer, ReverbType, SalmonItemStolen, SubEvent, UnstableChain, Weather};
+use itertools::Itertools;
+use uuid::Uuid;
+use crate::chronicler_schema::{Player, PlayerElsewhereInfo};
+use crate::formulas;
+use crate::game::Game;
+use crate::mods::{Mod, ModLayer};
+use crate::rng::Rng;
+use crate::sim::World;
+
+// TODO Figure out the correct thresholds for peanuts. The flavor text roll happens first
+const PEANUT_FLAVOR_THRESHOLD: f64 = 0.0002;
+const PEANUT_REACTION_THRESHOLD: f64 = 0.00035;
+// TODO Figure out how often the flood pumps stop a flood
+const FLOOD_PUMPS_THRESHOLD: f64 = 0.5;
+// TODO Figure out the correct odds for each salmon effect
+const SALMON_RUNS_LOST_THRESHOLD: f64 = 0.5;
+const SALMON_ITEM_THRESHOLD: f64 = 0.01;
+const SALMON_DECEASED_THRESHOLD: f64 = 0.01;
+
+// Shuffles the slots, leaving the ones that are `fixed` where they are. This is Fisher-Yates from
+// the back, one roll per movable slot but the first.
+// TODO We haven't confirmed this is the shuffle the server uses. Check it against a reverb in the
+//  feed once one comes up in a fragment
+fn reverb_shuffle(rng: &mut Rng, slots: &mut [Uuid], fixed: impl Fn(Uuid) -> bool) {
+    let movable = slots.iter()
+        .positions(|&id| !fixed(id))
+        .collect::<Vec<_>>();
+    for i in (1..movable.len()).rev() {
+        let j = (rng.next() * (i + 1) as f64) as usize;
+        slots.swap(movable[i], movable[j]);
+    }
+}
+
+impl Game {
+    pub(super) fn roll_weather(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        match self.weather {
+            Weather::Sun2 => { Ok(None) }
+            Weather::Snowy => { todo!() }
+            Weather::SolarEclipse => {
+                let threshold = formulas::eclipse_threshold(self.season, &self.ballpark(world));
+                self.roll_eclipse(world, rng, threshold)
+            }
+            // TODO Glitter and Jazz both have their own roll chains every pitch, but we don't know
+            //  their thresholds, how many rolls they use, or what they do to whoever they pick.
+            //  The RNG is shared between games, so skipping or guessing would throw off every
+            //  game after this one without saying so. Until they're worked out, games in these
+            //  weathers stop at their first pitch.
+            Weather::Glitter | Weather::Jazz => {
+                Err(anyhow!("{:?} weather isn't implemented yet", self.weather))
+            }
+            Weather::Blooddrain => { self.roll_blooddrain(world, rng) }
+            Weather::Peanuts => { self.roll_peanuts(world, rng) }
+            Weather::Birds => { self.roll_birds(world, rng) }
+            Weather::Feedback => { self.roll_feedback(world, rng) }
+            Weather::Reverb => { self.roll_reverb(world, rng) }
+            Weather::BlackHole => { Ok(None) }
+            Weather::Coffee => { Ok(None) }
+            Weather::Coffee2 => { Ok(None) }
+            Weather::Coffee3s => { Ok(None) }
+            // Flooding is rolled later in the pitch, by roll_flooding
+            Weather::Flooding => { Ok(None) }
+            // Salmon only swim between innings, in roll_salmon
+            Weather::Salmon => { Ok(None) }
+            Weather::PolarityPlus => { Ok(None) }
+            Weather::PolarityMinus => { Ok(None) }
+            Weather::Sun90 => { Ok(None) }
+            Weather::SunPoint1 => { Ok(None) }
+            Weather::SumSun => { Ok(None) }
+            Weather::SupernovaEclipse => {
+                let threshold = formulas::supernova_eclipse_threshold(&self.ballpark(world));
+                self.roll_eclipse(world, rng, threshold)
+            }
+            Weather::BlackHoleBlackHole => { Ok(None) }
+            Weather::Night => { self.roll_night(world, rng) }
+            _ => {
+                Err(anyhow!("Encountered weather {:?}, but it was never used", self.weather))
+            }
+        }
+    }
+
+    fn roll_eclipse(&mut self, world: &mut World, rng: &mut Rng, threshold: f64) -> anyhow::Result<Option<FedEventData>> {
+        let target = if rng.next() < threshold {
+            Some(self.roll_incineration_target(world, rng)?)
+        } else {
+            self.roll_unstable_incineration(world, rng)?
+        };
+        let Some((target_id, team_id)) = target else { return Ok(None) };
+
+        self.incinerate(world, rng, target_id, team_id).map(Some)
+    }
+
+    // The umpire picks a team, then anyone on that team who's playing in this game
+    fn roll_incineration_target(&self, world: &World, rng: &mut Rng) -> anyhow::Result<(Uuid, Uuid)> {
+        let team = if rng.next() < 0.5 { self.batting_team(world) } else { self.pitching_team(world) }
+            .ok_or_else(|| anyhow!("Couldn't find team to incinerate from"))?;
+        let candidates = team.lineup.iter().copied()
+            .chain(team.active_pitcher())
+            .collect::<Vec<_>>();
+        let target_id = candidates[(rng.next() * candidates.len() as f64) as usize];
+        Ok((target_id, team.id))
+    }
+
+    // Unstable batters and pitchers get an extra roll, lead by the batter
+    fn roll_unstable_incineration(&self, world: &World, rng: &mut Rng) -> anyhow::Result<Option<(Uuid, Uuid)>> {
+        let threshold = formulas::unstable_incineration_threshold(&self.ballpark(world));
+        let (batter, batting_team) = self.get_batter_and_team(world)?;
+        let (pitcher, pitching_team) = self.get_pitcher_and_team(world)?;
+        for (player, team) in [(batter, batting_team), (pitcher, pitching_team)] {
+            if !world.player_has_mod(player, Mod::Unstable, self.stadium_id) { continue; }
+            if rng.next() < threshold {
+                return Ok(Some((player.id, team.id)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn incinerate(&mut self, world: &mut World, rng: &mut Rng, target_id: Uuid, team_id: Uuid) -> anyhow::Result<FedEventData> {
+        let target = world.players.get(&target_id)
+            .ok_or_else(|| anyhow!("Couldn't find incineration target"))?;
+        let target_name = target.name.clone();
+
+        // Flickering doesn't change anything about an incineration. It only makes Feedback more
+        // likely, in roll_feedback.
+        if world.player_has_mod(target, Mod::Fireproof, self.stadium_id) {
+            return Ok(FedEventData::IncinerationBlocked {
+                game: self.game_event(),
+                target_name,
+            });
+        }
+
+        if world.player_has_mod(target, Mod::FireEater, self.stadium_id) {
+            let target = world.players.get_mut(&target_id)
+                .ok_or_else(|| anyhow!("Couldn't find incineration target"))?;
+            target.add_mod(ModLayer::Game, Mod::Magmatic);
+            return Ok(FedEventData::FireEater {
+                game: self.game_event(),
+                target_id,
+                target_name,
+                sub_event: SubEvent::nil(),
+            });
+        }
+
+        let was_unstable = world.player_has_mod(target, Mod::Unstable, self.stadium_id);
+
+        // The replacement is either pulled up from the shadows or a brand new player. A new player
+        // shows up in the world as a newcomer on this team, since Chronicler has them from the
+        // moment they join. Anyone else joining the team later in the fragment looks the same, so
+        // more than one is too ambiguous to go on.
+        let new_players = world.newcomers.values()
+            .filter(|newcomer| newcomer.league_team_id == Some(team_id))
+            .collect::<Vec<_>>();
+        if new_players.len() > 1 {
+            return Err(anyhow!("Can't tell which of {} newcomers to {team_id} replaced {target_name}", new_players.len()));
+        }
+        if let Some(new_player) = new_players.first() {
+            // New players roll their names, stats and everything else about them, and we don't know
+            // how many rolls that is. Guessing would quietly desync every game after this one.
+            return Err(anyhow!("{} replaced {target_name} as a new player, which the sim can't roll yet", new_player.name));
+        }
+
+        let team = world.teams.get_mut(&team_id)
+            .ok_or_else(|| anyhow!("Couldn't find incinerated player's team"))?;
+        let shadows = team.shadows.as_deref().unwrap_or_default();
+        if shadows.is_empty() {
+            return Err(anyhow!("{target_name} was incinerated, but their team has nobody in the shadows to replace them"));
+        }
+        let replacement_id = shadows[(rng.next() * shadows.len() as f64) as usize];
+        team.remove_from_reserves(replacement_id);
+        if !team.replace_player(target_id, replacement_id) {
+            return Err(anyhow!("Incinerated player wasn't on their team's roster"));
+        }
+        let team_nickname = team.nickname.clone();
+
+        let replacement = world.players.get_mut(&replacement_id)
+            .ok_or_else(|| anyhow!("Couldn't find incineration replacement"))?;
+        replacement.league_team_id = Some(team_id);
+        let replacement_name = replacement.name.clone();
+        if let Some(target) = world.players.get_mut(&target_id) {
+            target.league_team_id = None;
+            target.deceased = Some(true);
+            // A Spicy player's streak goes with them
+            target.remove_mod(&Mod::HeatingUp);
+            target.remove_mod(&Mod::RedHot);
+        }
+        self.baserunners.retain(|runner| runner.id != target_id);
+
+        // An Unstable player's instability jumps to someone on the other team when they go
+        let chain = if was_unstable {
+            let other_team_id = if team_id == self.home.team_id { self.away.team_id } else { self.home.team_id };
+            let other_team = world.teams.get(&other_team_id)
+                .ok_or_else(|| anyhow!("Couldn't find team for unstable chain"))?;
+            let chain_target_id = other_team.lineup[(rng.next() * other_team.lineup.len() as f64) as usize];
+            let chain_team_nickname = other_team.nickname.clone();
+            let chain_target = world.players.get_mut(&chain_target_id)
+                .ok_or_else(|| anyhow!("Couldn't find unstable chain target"))?;
+            chain_target.add_mod(ModLayer::Seas, Mod::Unstable);
+            Some(UnstableChain {
+                team_nickname: chain_team_nickname,
+                target_id: chain_target_id,
+                target_name: chain_target.name.clone(),
+            })
+        } else {
+            None
+        };
+
+        Ok(FedEventData::Incineration {
+            game: self.game_event(),
+            victim_id: target_id,
+            victim_name: target_name,
+            team_nickname,
+            replacement_id,
+            replacement_name,
+            chain,
+            sub_event: SubEvent::nil(),
+        })
+    }
+
+    fn roll_feedback(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        let batter = self.get_batter(world)?;
+        let pitcher = self.get_pitcher(world)?;
+        let batter_flickering = world.player_has_mod(batter, Mod::Flickering, self.stadium_id);
+        let pitcher_flickering = world.player_has_mod(pitcher, Mod::Flickering, self.stadium_id);
+        let threshold = formulas::feedback_threshold(batter_flickering || pitcher_flickering);
+        if rng.next() >= threshold { return Ok(None); }
+
+        // Batters swap with one of the other team's fielders, and pitchers with one of the other
+        // team's pitchers. The batter-or-pitcher roll is always made, but if only one of them is
+        // Flickering, they're the one who gets swapped.
+        let batting_team = self.batting_team(world)
+            .ok_or_else(|| anyhow!("Couldn't find batting team"))?;
+        let pitching_team = self.pitching_team(world)
+            .ok_or_else(|| anyhow!("Couldn't find pitching team"))?;
+        let batter_roll = rng.next() < 0.5;
+        let swap_batter = if batter_flickering != pitcher_flickering { batter_flickering } else { batter_roll };
+        let (player, player_team, target_team, target_roster) = if swap_batter {
+            (batter, batting_team, pitching_team, &pitching_team.lineup)
+        } else {
+            (pitcher, pitching_team, batting_team, &batting_team.rotation)
+        };
+        let target_id = target_roster[(rng.next() * target_roster.len() as f64) as usize];
+        let target = world.players.get(&target_id)
+            .ok_or_else(|| anyhow!("Couldn't find feedback target"))?;
+
+        let (player_id, player_name) = (player.id, player.name.clone());
+        let (target_name, player_team_id, target_team_id) = (target.name.clone(), player_team.id, target_team.id);
+
+        // Either player being Soundproof stops the swap
+        for soundproof in [player, target] {
+            if world.player_has_mod(soundproof, Mod::Soundproof, self.stadium_id) {
+                let soundproof_name = soundproof.name.clone();
+                return Ok(Some(FedEventData::FeedbackBlocked {
+                    game: self.game_event(),
+                    player_name: soundproof_name,
+                    other_name: if soundproof.id == player_id { target_name } else { player_name },
+                }));
+            }
+        }
+
+        self.swap_players(world, (player_id, player_team_id), (target_id, target_team_id))?;
+
+        Ok(Some(FedEventData::FeedbackSwap {
+            game: self.game_event(),
+            player1_id: player_id,
+            player1_name: player_name,
+            player2_id: target_id,
+            player2_name: target_name,
+            sub_event: SubEvent::nil(),
+        }))
+    }
+
+    // Each player takes the other's place on the other's team
+    fn swap_players(&mut self, world: &mut World, (a_id, a_team_id): (Uuid, Uuid), (b_id, b_team_id): (Uuid, Uuid)) -> anyhow::Result<()> {
+        let a_team = world.teams.get_mut(&a_team_id)
+            .ok_or_else(|| anyhow!("Couldn't find team to swap from"))?;
+        if !a_team.replace_player(a_id, b_id) {
+            return Err(anyhow!("Swapped player {a_id} wasn't on their team's roster"));
+        }
+        let b_team = world.teams.get_mut(&b_team_id)
+            .ok_or_else(|| anyhow!("Couldn't find team to swap from"))?;
+        if !b_team.replace_player(b_id, a_id) {
+            return Err(anyhow!("Swapped player {b_id} wasn't on their team's roster"));
+        }
+
+        for (player_id, team_id) in [(a_id, b_team_id), (b_id, a_team_id)] {
+            let player = world.players.get_mut(&player_id)
+                .ok_or_else(|| anyhow!("Couldn't find swapped player"))?;
+            player.league_team_id = Some(team_id);
+        }
+
+        // A swapped baserunner is still the same person on the same base, just for the other team
+        // now. Nobody in the field is on base, so there's nothing to do about them.
+        Ok(())
+    }
+
+    fn roll_blooddrain(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        let batter = self.get_batter(world)?;
+        let pitcher = self.get_pitcher(world)?;
+        let (batter_id, pitcher_id) = (batter.id, pitcher.id);
+        let batting_team_id = self.batting_team_game_data().team_id;
+        let pitching_team_id = self.pitching_team_game_data().team_id;
+
+        // Siphons get to drain before the regular blooddrain roll, batter first
+        let mut drain = None;
+        for (player, other_team_id) in [(batter, pitching_team_id), (pitcher, batting_team_id)] {
+            if world.player_has_mod(player, Mod::Siphon, self.stadium_id) && rng.next() < formulas::siphon_threshold() {
+                drain = Some((player.id, other_team_id, true));
+                break;
+            }
+        }
+        if drain.is_none() && rng.next() < formulas::blooddrain_threshold() {
+            drain = Some(if rng.next() < 0.5 {
+                (batter_id, pitching_team_id, false)
+            } else {
+                (pitcher_id, batting_team_id, false)
+            });
+        }
+        let Some((drainer_id, target_team_id, is_siphon)) = drain else { return Ok(None) };
+
+        let target_team = world.teams.get(&target_team_id)
+            .ok_or_else(|| anyhow!("Couldn't find team to drain from"))?;
+        let target_id = target_team.lineup[(rng.next() * target_team.lineup.len() as f64) as usize];
+
+        let category = match (rng.next() * 4.) as i32 {
+            0 => BlooddrainCategory::Pitching,
+            1 => BlooddrainCategory::Batting,
+            2 => BlooddrainCategory::Defense,
+            _ => BlooddrainCategory::Baserunning,
+        };
+
+        // TODO Figure out how much a blooddrain takes. Until then there's no honest way to change
+        //  the stats, and every later roll that depends on them would be off, so stop here. Once
+        //  it's known, the drainer gains what the target loses across the whole category, and a
+        //  siphon uses it right away if their side of the ball can: batting adds a ball and
+        //  baserunning a run for a batter, pitching adds a strike and defense an out for a pitcher.
+        let drainer_name = world.players.get(&drainer_id).map_or("Someone", |player| player.name.as_str());
+        let target_name = world.players.get(&target_id).map_or("someone", |player| player.name.as_str());
+        let kind = if is_siphon { "siphoned" } else { "blooddrained" };
+        Err(anyhow!("{drainer_name} {kind} {category:?} from {target_name}, but the sim doesn't know how much a drain takes yet"))
+    }
+
+    fn roll_peanuts(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        if rng.next() < PEANUT_FLAVOR_THRESHOLD {
+            // The text is picked with a roll too. We don't have the list it picks from, so the
+            // roll is used up and the text is left empty (Sim::check_next_event doesn't compare it).
+            rng.next();
+            return Ok(Some(FedEventData::PeanutFlavorText {
+                game: self.game_event(),
+                text: String::new(),
+            }));
+        }
+
+        if rng.next() >= PEANUT_REACTION_THRESHOLD { return Ok(None); }
+
+        // Either the batter or the pitcher swallows a stray peanut
+        let target_id = if rng.next() < 0.5 { self.get_batter(world)?.id } else { self.get_pitcher(world)?.id };
+        let target = world.players.get(&target_id)
+            .ok_or_else(|| anyhow!("Couldn't find peanut target"))?;
+        let is_allergic = target.peanut_allergy.unwrap_or(false);
+
+        // Everyone who isn't allergic has a yummy reaction instead. Either way the reaction
+        // changes the player's stats by rolled amounts, and we don't know those rolls, so stop
+        // rather than let the RNG fall out of step.
+        // TODO Roll the stat changes
+        let reaction = if is_allergic { "an allergic" } else { "a yummy" };
+        Err(anyhow!("{} had {reaction} reaction to a peanut, but the sim can't roll its stat changes yet", target.name))
+    }
+
+    // Affinity for Crows is the other half of birds weather, but that's a stat boost and lives in
+    // stats::multiplier
+    fn roll_birds(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        let stadium_birds = self.stadium(world).map_or(0, |stadium| stadium.birds);
+
+        // The birds pick at Shelled players first, batting team then pitching team
+        let shelled = [self.batting_team(world), self.pitching_team(world)].into_iter()
+            .flatten()
+            .flat_map(|team| team.lineup.iter())
+            .filter_map(|id| world.players.get(id))
+            .filter(|player| world.player_has_mod(player, Mod::Shelled, self.stadium_id))
+            .map(|player| player.id)
+            .collect::<Vec<_>>();
+        for player_id in shelled {
+            if rng.next() >= formulas::unshell_threshold(stadium_birds) { continue; }
+
+            let player = world.players.get_mut(&player_id)
+                .ok_or_else(|| anyhow!("Couldn't find Shelled player"))?;
+            player.remove_mod(&Mod::Shelled);
+            return Ok(Some(FedEventData::BirdsUnshell {
+                game: self.game_event(),
+                player_id,
+                player_name: player.name.clone(),
+                sub_event: SubEvent::nil(),
+            }));
+        }
+
+        if rng.next() < formulas::birds_threshold(stadium_birds) {
+            // Like peanut flavor text, the message is picked with a roll from a list we don't have
+            rng.next();
+            return Ok(Some(FedEventData::BirdsFlavorText {
+                game: self.game_event(),
+                text: String::new(),
+            }));
+        }
+
+        Ok(None)
+    }
+
+    fn roll_reverb(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        if rng.next() >= formulas::reverb_threshold() { return Ok(None); }
+
+        let team_id = if rng.next() < 0.5 { self.home.team_id } else { self.away.team_id };
+        // TODO These odds are guesses. Figure out the real odds of each kind of shuffle
+        let type_roll = rng.next();
+        let reverb_type = if type_roll < 0.09 {
+            ReverbType::Full
+        } else if type_roll < 0.55 {
+            ReverbType::Lineup
+        } else if type_roll < 0.9 {
+            ReverbType::Rotation
+        } else {
+            ReverbType::Several
+        };
+
+        // Gravity players stay put no matter what kind of shuffle it is
+        let team = world.teams.get(&team_id)
+            .ok_or_else(|| anyhow!("Couldn't find team to reverb"))?;
+        let has_gravity = |id: Uuid| world.players.get(&id).is_some_and(|player| world.player_has_mod(player, Mod::Gravity, self.stadium_id));
+        let lineup_len = team.lineup.len();
+        let mut slots = match reverb_type {
+            ReverbType::Lineup => team.lineup.clone(),
+            ReverbType::Rotation => team.rotation.clone(),
+            ReverbType::Full | ReverbType::Several => team.lineup.iter().chain(&team.rotation).copied().collect(),
+        };
+        let gravity_names = slots.iter()
+            .filter(|&&id| has_gravity(id))
+            .filter_map(|id| world.players.get(id))
+            .map(|player| player.name.clone())
+            .collect::<Vec<_>>();
+
+        match reverb_type {
+            ReverbType::Several => {
+                // Each slot gets a roll to decide whether it's part of the shuffle
+                // TODO Both the per-slot roll and its even odds are guesses. Check them against a
+                //  Several reverb in the feed
+                let included = slots.iter()
+                    .map(|&id| !has_gravity(id) && rng.next() < 0.5)
+                    .collect::<Vec<_>>();
+                let mut picked = slots.iter().zip(&included)
+                    .filter(|(_, &included)| included)
+                    .map(|(&id, _)| id)
+                    .collect::<Vec<_>>();
+                reverb_shuffle(rng, &mut picked, |_| false);
+                let mut picked = picked.into_iter();
+                for (slot, included) in slots.iter_mut().zip(included) {
+                    if included {
+                        *slot = picked.next()
+                            .ok_or_else(|| anyhow!("Reverb picked fewer players than slots"))?;
+                    }
+                }
+            }
+            _ => reverb_shuffle(rng, &mut slots, has_gravity),
+        }
+
+        let team = world.teams.get_mut(&team_id)
+            .ok_or_else(|| anyhow!("Couldn't find team to reverb"))?;
+        match reverb_type {
+            ReverbType::Lineup => team.lineup = slots,
+            ReverbType::Rotation => team.rotation = slots,
+            ReverbType::Full | ReverbType::Several => {
+                // The roster keeps its shape, so whoever lands in the first lineup_len slots bats
+                team.rotation = slots.split_off(lineup_len);
+                team.lineup = slots;
+            }
+        }
+        let team_nickname = team.nickname.clone();
+
+        Ok(Some(FedEventData::ReverbRosterShuffle {
+            game: self.game_event(),
+            team_nickname,
+            reverb_type,
+            gravity_players: gravity_names,
+            sub_event: SubEvent::nil(),
+        }))
+    }
+
+    // Floods only happen with runners on base, and sweep all of them away unless something stops
+    // it. This comes after the weather roll, so it's called from pitch() directly.
+    pub(super) fn roll_flooding(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        if self.weather != Weather::Flooding || self.baserunners.is_empty() { return Ok(None); }
+        if rng.next() >= formulas::flood_threshold(&self.ballpark(world)) { return Ok(None); }
+
+        if self.stadium(world).is_some_and(|stadium| stadium.has_mod(Mod::FloodPumps)) &&
+            rng.next() < FLOOD_PUMPS_THRESHOLD {
+            return Ok(Some(FedEventData::FloodPumps {
+                game: self.game_event(),
+                sub_event: SubEvent::nil(),
+            }));
+        }
+
+        let mut swept = Vec::new();
+        let mut scored = Vec::new();
+        let mut stayed = Vec::new();
+        for runner in std::mem::take(&mut self.baserunners) {
+            let player = world.players.get(&runner.id)
+                .ok_or_else(|| anyhow!("Couldn't find baserunner"))?;
+            // Ego and Immaterial runners can't be swept away
+            let unsweepable = [Mod::Ego1, Mod::Ego2, Mod::Ego3, Mod::Ego4, Mod::Immaterial].into_iter()
+                .any(|m| world.player_has_mod(player, m, self.stadium_id));
+            if unsweepable {
+                stayed.push(runner);
+                continue;
+            }
+
+            // Swim bladders carry runners home instead of Elsewhere
+            let swim_bladder = world.player_has_mod(player, Mod::SwimBladder, self.stadium_id);
+            swept.push(FloodingSweptPlayer {
+                player_id: runner.id,
+                player_name: runner.name.clone(),
+                swim_bladder,
+            });
+            if swim_bladder {
+                scored.push(runner);
+            } else {
+                let player = world.players.get_mut(&runner.id)
+                    .ok_or_else(|| anyhow!("Couldn't find baserunner"))?;
+                player.add_mod(ModLayer::Perm, Mod::Elsewhere);
+                player.state.get_or_insert_with(Default::default).elsewhere = Some(PlayerElsewhereInfo {
+                    day: self.day,
+                    season: self.season,
+                });
+            }
+        }
+        self.baserunners = stayed;
+        let scores = self.score_runners(scored);
+
+        Ok(Some(FedEventData::FloodingSwept {
+            game: self.game_event(),
+            swept,
+            scores,
+            sub_event: SubEvent::nil(),
+        }))
+    }
+
+    // The salmon swim upstream and the inning that just ended starts over. Each team might lose
+    // the runs they scored in it, and the salmon sometimes make off with an item.
+    pub(super) fn roll_salmon(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        if self.weather != Weather::Salmon { return Ok(None); }
+        if rng.next() >= formulas::salmon_threshold(&self.ballpark(world)) { return Ok(None); }
+
+        let (away_start, home_start) = self.inning_start_scores;
+        let away_runs = self.away.score - away_start;
+        let home_runs = self.home.score - home_start;
+        let away_runs_lost = (away_runs > 0. && rng.next() < SALMON_RUNS_LOST_THRESHOLD).then_some(away_runs);
+        let home_runs_lost = (home_runs > 0. && rng.next() < SALMON_RUNS_LOST_THRESHOLD).then_some(home_runs);
+        if let Some(runs) = away_runs_lost { self.away.score -= runs; }
+        if let Some(runs) = home_runs_lost { self.home.score -= runs; }
+
+        let item_stolen = if rng.next() < SALMON_ITEM_THRESHOLD {
+            let candidates = [self.away.team_id, self.home.team_id].iter()
+                .filter_map(|team_id| world.teams.get(team_id))
+                .flat_map(|team| team.lineup.iter().chain(&team.rotation))
+                .filter(|id| world.players.get(id).is_some_and(|player| !player.items().is_empty()))
+                .copied()
+                .collect::<Vec<_>>();
+            if candidates.is_empty() {
+                None
+            } else {
+                let player_id = candidates[(rng.next() * candidates.len() as f64) as usize];
+                let player = world.players.get_mut(&player_id)
+                    .ok_or_else(|| anyhow!("Couldn't find player for salmon to steal from"))?;
+                let index = (rng.next() * player.items().len() as f64) as usize;
+                let item = player.remove_item(index)?;
+                Some(SalmonItemStolen {
+                    player_name: player.name.clone(),
+                    item_id: item.id,
+                    item_name: item.name,
+                })
+            }
+        } else {
+            None
+        };
+
+        // The salmon can also bring a deceased player back with them. Who comes back, and how,
+        // isn't modeled yet, so stop rather than carry on with the wrong roster.
+        if rng.next() < SALMON_DECEASED_THRESHOLD {
+            return Err(anyhow!("Salmon brought back a deceased player, which the sim doesn't handle yet"));
+        }
+
+        // Going back to the bottom of the previous inning means the next half-inning start is the
+        // top of this one again. start_half_inning knows not to roll for salmon again.
+        let inning = self.inning;
+        self.inning -= 1;
+
+        Ok(Some(FedEventData::Salmon {
+            game: self.game_event(),
+            inning: inning + 1, // one-indexed
+            away_team_runs_lost: away_runs_lost,
+            home_team_runs_lost: home_runs_lost,
+            item_stolen,
+            sub_event: SubEvent::nil(),
+        }))
+    }
+
+    // A shadow comes out and takes over for the batter or the pitcher. Night brings out whoever in
+    // the shadows is best at the job, judged by their star rating for it.
+    fn roll_night(&mut self, world: &mut World, rng: &mut Rng) -> anyhow::Result<Option<FedEventData>> {
+        if rng.next() >= formulas::night_threshold() { return Ok(None); }
+
+        let is_batter = rng.next() < 0.5;
+        // The pitcher is whoever's up in the rotation, never someone from the lineup
+        let (player, team) = if is_batter { self.get_batter_and_team(world)? } else { self.get_pitcher_and_team(world)? };
+        let (player_id, player_name, team_id) = (player.id, player.name.clone(), team.id);
+
+        let rating = |player: &Player| if is_batter {
+            player.hitting_rating.unwrap_or(0.)
+        } else {
+            player.pitching_rating.unwrap_or(0.)
+        };
+        let shadow = world.shadows_on_team(team_id)
+            .ok_or_else(|| anyhow!("Couldn't find team for night swap"))?
+            .flatten()
+            // Best rating first, and ties go to whoever's earlier in the shadows
+            .min_by(|a, b| rating(b).total_cmp(&rating(a)));
+        let Some(shadow) = shadow else { return Ok(None) };
+        let (shadow_id, shadow_name) = (shadow.id, shadow.name.clone());
+
+        let team = world.teams.get_mut(&team_id)
+            .ok_or_else(|| anyhow!("Couldn't find team for night swap"))?;
+        if !team.swap_with_shadow(player_id, shadow_id) {
+            return Err(anyhow!("Night swap target wasn't in the shadows"));
+        }
+
+        Ok(Some(FedEventData::NightShadowSwap {
+            game: self.game_event(),
+            player_id,
+            player_name,
+            shadow_id,
+            shadow_name,
+            is_batter,
+            sub_event: SubEvent::nil(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fed::GameEvent;
+    use crate::chronicler_schema::Team;
+    use crate::stats;
+    use super::*;
+
+    const HOME_TEAM: Uuid = Uuid::from_u128(0x100);
+    const AWAY_TEAM: Uuid = Uuid::from_u128(0x200);
+
+    fn ids(n: u128) -> Vec<Uuid> {
+        (1..=n).map(Uuid::from_u128).collect()
+    }
+
+    #[test]
+    fn reverb_shuffle_is_a_permutation() {
+        let original = ids(9);
+        let mut slots = original.clone();
+        reverb_shuffle(&mut Rng::new(1, 2), &mut slots, |_| false);
+        assert_eq!(slots.iter().sorted().collect_vec(), original.iter().sorted().collect_vec());
+    }
+
+    #[test]
+    fn reverb_shuffle_leaves_fixed_slots() {
+        let original = ids(9);
+        let fixed = [original[0], original[4], original[8]];
+        for seed in 0..20 {
+            let mut slots = original.clone();
+            reverb_shuffle(&mut Rng::new(seed, seed + 1), &mut slots, |id| fixed.contains(&id));
+            for i in [0, 4, 8] {
+                assert_eq!(slots[i], original[i]);
+            }
+        }
+    }
+
+    // The next roll from an Rng that's already made `n` rolls
+    fn roll_after(s0: u64, s1: u64, n: usize) -> f64 {
+        let mut rng = Rng::new(s0, s1);
+        for _ in 0..n {
+            rng.next();
+        }
+        rng.next()
+    }
+
+    // The expected orders were worked out separately from the same seeds
+    #[test]
+    fn reverb_shuffle_from_known_seeds() {
+        let original = ids(9);
+
+        let mut slots = original.clone();
+        let mut rng = Rng::new(3, 4);
+        reverb_shuffle(&mut rng, &mut slots, |_| false);
+        assert_eq!(slots, [6, 9, 5, 8, 3, 2, 4, 1, 7].map(Uuid::from_u128));
+        // One roll for every slot but the first
+        assert_eq!(rng.next(), roll_after(3, 4, 8));
+
+        let fixed = [original[0], original[4], original[8]];
+        let mut slots = original.clone();
+        let mut rng = Rng::new(1, 2);
+        reverb_shuffle(&mut rng, &mut slots, |id| fixed.contains(&id));
+        assert_eq!(slots, [1, 3, 7, 4, 5, 2, 8, 6, 9].map(Uuid::from_u128));
+        // Fixed slots don't get a roll
+        assert_eq!(rng.next(), roll_after(1, 2, 5));
+    }
+
+    // A team with a three-player lineup, a two-player rotation and three shadows, whose hitting
+    // and pitching ratings are 0.2, 0.9 and 0.9. Everyone on the active roster is rated 0.5.
+    fn team_with_players(id: Uuid) -> (Team, Vec<Player>) {
+        let ids = |start: u128, len: u128| (start..start + len).map(|n| Uuid::from_u128(id.as_u128() + n)).collect::<Vec<_>>();
+        let mut team = stats::tests::team(id, 0, 0, Vec::new());
+        team.lineup = ids(1, 3);
+        team.rotation = ids(4, 2);
+        team.shadows = Some(ids(6, 3));
+
+        let ratings = team.lineup.iter().chain(&team.rotation).map(|&player_id| (player_id, 0.5))
+            .chain(ids(6, 3).into_iter().zip([0.2, 0.9, 0.9]));
+        let players = ratings
+            .map(|(player_id, rating)| {
+                let mut player = stats::tests::player(id, Vec::new());
+                player.id = player_id;
+                player.hitting_rating = Some(rating);
+                player.pitching_rating = Some(rating);
+                player
+            })
+            .collect();
+        (team, players)
+    }
+
+    fn night_game() -> (Game, World) {
+        let (home, home_players) = team_with_players(HOME_TEAM);
+        let (away, away_players) = team_with_players(AWAY_TEAM);
+        let mut world = stats::tests::world(vec![home, away]);
+        world.players = home_players.into_iter().chain(away_players)
+            .map(|player| (player.id, player))
+            .collect();
+
+        let game_event = GameEvent {
+            game_id: Uuid::from_u128(0x300),
+            home_team: HOME_TEAM,
+            away_team: AWAY_TEAM,
+            play: 0,
+            unscatter: None,
+            attractor_secret_base: None,
+        };
+        let mut game = Game::new(&game_event, Weather::Night, None, 13, 0);
+        // The bottom of the first, with the home team's first batter up
+        game.inning = 0;
+        game.home.team_batter_count = 0;
+        (game, world)
+    }
+
+    // The seeds were picked so night comes out on the first roll, then the second roll picks the
+    // pitcher for one and the batter for the other
+    #[test]
+    fn night_swaps_the_pitcher_with_the_best_shadow_pitcher() {
+        let (mut game, mut world) = night_game();
+        let event = game.roll_night(&mut world, &mut Rng::new(120, 841)).unwrap();
+
+        // The away team is pitching. Their first shadow with the best rating comes out, and the
+        // pitcher takes their place in the shadows.
+        let pitcher = Uuid::from_u128(0x204);
+        let shadow = Uuid::from_u128(0x207);
+        assert!(matches!(event, Some(FedEventData::NightShadowSwap { player_id, shadow_id, is_batter: false, .. })
+            if player_id == pitcher && shadow_id == shadow));
+        let team = &world.teams[&AWAY_TEAM];
+        assert_eq!(team.rotation, vec![shadow, Uuid::from_u128(0x205)]);
+        assert_eq!(team.shadows.as_deref().unwrap(), [Uuid::from_u128(0x206), pitcher, Uuid::from_u128(0x208)]);
+    }
+
+    #[test]
+    fn night_swaps_the_batter_with_the_best_shadow_hitter() {
+        let (mut game, mut world) = night_game();
+        let event = game.roll_night(&mut world, &mut Rng::new(61, 428)).unwrap();
+
+        let batter = Uuid::from_u128(0x101);
+        let shadow = Uuid::from_u128(0x107);
+        assert!(matches!(event, Some(FedEventData::NightShadowSwap { player_id, shadow_id, is_batter: true, .. })
+            if player_id == batter && shadow_id == shadow));
+        let team = &world.teams[&HOME_TEAM];
+        assert_eq!(team.lineup, vec![shadow, Uuid::from_u128(0x102), Uuid::from_u128(0x103)]);
+        assert_eq!(team.rotation, vec![Uuid::from_u128(0x104), Uuid::from_u128(0x105)]);
+    }
+}
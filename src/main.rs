@@ -1,25 +1,24 @@
 #![feature(iterator_try_collect)]
-#![feature(file_create_new)]
 
 mod sim;
 mod game;
 mod rng;
+mod stats;
 mod chronicler_schema;
+mod chron;
+mod chron_dump;
+mod world_store;
+mod formulas;
+mod mods;
+mod schema_drift;
 
-use std::collections::HashMap;
-use std::fs::File;
-use std::path::Path;
-use std::io::{BufReader, BufWriter};
-use futures::{pin_mut, StreamExt, TryStreamExt};
-use itertools::Itertools;
+use anyhow::anyhow;
 use chrono::{DateTime, Utc};
 use fed;
-use uuid::Uuid;
-use crab::chron;
-use serde::Deserialize;
 
-use crate::chronicler_schema::{Player, Team};
+use crate::chron::ChronClient;
 use crate::sim::{Sim, World};
+use crate::world_store::WorldStore;
 
 type Fragment = (i64, (u64, u64), i64, i64, &'static str, &'static str);
 
@@ -243,20 +242,47 @@ const FRAGMENTS: [Fragment; 108] = [
     // deploy at 2021-06-20T17:35:00Z
 ];
 
-const CHRON_API_ENDPOINT: &'static str = "https://api.sibr.dev/chronicler/v2/entities";
+// Used when the CHRON_API_ENDPOINT environment variable isn't set. Setting CHRON_FIXTURES to a
+// directory instead serves everything from fixture files (see chron.rs), and setting CHRON_DUMP
+// serves it from local data dumps (see chron_dump.rs). Neither touches the network.
+const DEFAULT_CHRON_API_ENDPOINT: &'static str = "https://api.sibr.dev/chronicler/v2";
 
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let client = reqwest::Client::new();
+    let client = chron_client_from_env()?;
+    // Runs against fixtures should point this somewhere else so they don't mix with real data
+    let store_path = std::env::var("WORLD_STORE").unwrap_or_else(|_| "world_store".to_string());
+    let store = WorldStore::open(&store_path)?;
+    // By default the world only changes the way the sim changes it. With this set, Chronicler's
+    // versions of every entity are applied as the events reach them too.
+    let apply_chron_versions = std::env::var("APPLY_CHRON_VERSIONS").is_ok_and(|value| value != "0");
+
+    let args = std::env::args().collect::<Vec<_>>();
+    match args.get(1).map(String::as_str) {
+        None => {}
+        Some("schema-drift") => {
+            let [_, _, start, end] = args.as_slice() else {
+                return Err(anyhow!("Usage: {} schema-drift <start time> <end time>", args[0]));
+            };
+            return schema_drift::run(&store, &client, start.parse()?, end.parse()?).await;
+        }
+        Some(command) => return Err(anyhow!("Unknown command {command}")),
+    }
     let mut event_iter = fed::expansion_era_events();
     let mut skipping: Option<u32> = None;
     'fragment_loop: for fragment in FRAGMENTS {
         let (_season, (s0, s1), _offset, _rng_step, start_time, end_time) = fragment;
-        let world = get_world_at_time(&client, start_time).await?;
-        let mut sim_state = Sim::new(s0, s1, world);
         let start_date: DateTime<Utc> = start_time.parse()?;
         let end_date: DateTime<Utc> = end_time.parse()?;
+        let world = get_world_at_time(&store, &client, start_date, end_date).await?;
+        for unknown_mod in world.unknown_mods() {
+            println!("Warning: Unrecognized mod {unknown_mod}");
+        }
+        let mut sim_state = Sim::new(s0, s1, world);
+        if apply_chron_versions {
+            sim_state = sim_state.with_updates(store.updates_between(start_date, end_date)?);
+        }
         while let Some(event) = event_iter.next() {
             let event = event?;
             if event.created >= end_date {
@@ -284,128 +310,24 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn get_world_at_time(client: &reqwest::Client, start_time_str: &str) -> anyhow::Result<World> {
-    let world_cache_folder = Path::new("world_cache");
-    std::fs::create_dir_all(&world_cache_folder)?;
-
-    let world_path = world_cache_folder.join(format!("{start_time_str}.bin"));
-    let world_file = match File::create_new(&world_path) {
-        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
-            // This is the common case, where the world is already saved
-            println!("Reading world from cache");
-            let reader = BufReader::new(File::open(&world_path)?);
-            return Ok(bincode::deserialize_from(reader)?)
-        }
-        other => other?
-    };
-
-    println!("Loading world from network (this may take many seconds)");
-    let world = get_world_at_time_from_network(client, start_time_str).await?;
-    let w = BufWriter::new(world_file);
-    bincode::serialize_into(w, &world)?;
-    Ok(world)
-}
-
-
-async fn get_world_at_time_from_network(client: &reqwest::Client, start_time_str: &str) -> anyhow::Result<World> {
-    let start_time = DateTime::parse_from_rfc3339(start_time_str)?.with_timezone(&Utc);
-
-    #[derive(Debug, Deserialize)]
-    struct Sim {
-        league: Uuid,
-    }
-
-    let league = chron::v2::fetch::<Sim>(client, CHRON_API_ENDPOINT, chron::v2::RequestBuilder::default()
-        .ty("sim")
-        .id(Uuid::nil())
-        .at(start_time)
-        .build()?)
-        .try_collect::<Vec<_>>().await?
-        .into_iter()
-        .exactly_one()?
-        .data.league;
-
-    #[derive(Debug, Deserialize)]
-    struct League {
-        subleagues: [Uuid; 2],
+fn chron_client_from_env() -> anyhow::Result<ChronClient> {
+    if let Ok(dir) = std::env::var("CHRON_FIXTURES") {
+        println!("Using Chronicler fixtures from {dir}");
+        return Ok(ChronClient::from_fixtures(dir));
     }
-
-    let subleagues = chron::v2::fetch::<League>(client, CHRON_API_ENDPOINT, chron::v2::RequestBuilder::default()
-        .ty("league")
-        .id(league)
-        .at(start_time)
-        .build()?)
-        .try_collect::<Vec<_>>().await?
-        .into_iter()
-        .flat_map(|league| league.data.subleagues)
-        .collect::<Vec<_>>();
-
-
-    #[derive(Debug, Deserialize)]
-    struct Subleague {
-        divisions: [Uuid; 2],
-    }
-
-    let divisions = chron::v2::fetch::<Subleague>(client, CHRON_API_ENDPOINT, chron::v2::RequestBuilder::default()
-        .ty("subleague")
-        .id(subleagues.into_iter().map(|s| s.to_string()).join(","))
-        .at(start_time)
-        .build()?)
-        .try_collect::<Vec<_>>().await?
-        .into_iter()
-        .flat_map(|subleague| subleague.data.divisions)
-        .collect::<Vec<_>>();
-
-
-    #[derive(Debug, Deserialize)]
-    struct Division {
-        teams: Vec<Uuid>,
-    }
-
-    let team_ids = chron::v2::fetch::<Division>(client, CHRON_API_ENDPOINT, chron::v2::RequestBuilder::default()
-        .ty("division")
-        .id(divisions.into_iter().map(|s| s.to_string()).join(","))
-        .at(start_time)
-        .build()?)
-        .try_collect::<Vec<_>>().await?
-        .into_iter()
-        .flat_map(|league| league.data.teams)
-        .collect::<Vec<_>>();
-
-    let mut teams = HashMap::new();
-    let team_stream = chron::v2::fetch::<Team>(client, CHRON_API_ENDPOINT, chron::v2::RequestBuilder::default()
-        .ty("team")
-        .id(team_ids.into_iter().map(|s| s.to_string()).join(","))
-        .at(start_time)
-        .build()?);
-    pin_mut!(team_stream);
-    while let Some(team) = team_stream.next().await {
-        let team = team?;
-        let id = Uuid::parse_str(&team.entity_id)?;
-        teams.insert(id, team.data);
-    }
-
-    println!("Fetched {} teams", teams.len());
-
-    // TODO Fetch in parallel
-    let mut players = HashMap::new();
-    for team in teams.values() {
-        let player_ids = team.lineup.iter().chain(team.rotation.iter())
-            .map(|id| id.to_string())
-            .join(",");
-        let player_stream = chron::v2::fetch::<Player>(client, CHRON_API_ENDPOINT, chron::v2::RequestBuilder::default()
-            .ty("player")
-            .id(player_ids)
-            .at(start_time)
-            .build()?);
-        pin_mut!(player_stream);
-        while let Some(player) = player_stream.next().await {
-            let player = player?;
-            let id = Uuid::parse_str(&player.entity_id)?;
-            players.insert(id, player.data);
-        }
-        println!("Fetched {} players...", players.len());
+    if let Ok(dir) = std::env::var("CHRON_DUMP") {
+        println!("Using Chronicler dumps from {dir}");
+        return ChronClient::from_dump(dir);
     }
+    let endpoint = std::env::var("CHRON_API_ENDPOINT")
+        .unwrap_or_else(|_| DEFAULT_CHRON_API_ENDPOINT.to_string());
+    Ok(ChronClient::new(reqwest::Client::new(), &endpoint))
+}
 
-    Ok(World { teams, players })
+async fn get_world_at_time(store: &WorldStore, client: &ChronClient, start_time: DateTime<Utc>, end_time: DateTime<Utc>) -> anyhow::Result<World> {
+    // This is a no-op in the common case, where the store already has everything
+    store.populate(client, start_time, end_time).await?;
+    let mut world = store.world_at(start_time)?;
+    world.newcomers = store.newcomers(&world, start_time, end_time)?;
+    Ok(world)
 }
@@ -0,0 +1,242 @@
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use uuid::Uuid;
+
+macro_rules! mods {
+    ($($variant:ident => $name:literal,)*) => {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub enum Mod {
+            $($variant,)*
+            // Anything we don't recognize. These are reported when the world is loaded so they can
+            // be added to the list
+            Unknown(String),
+        }
+
+        impl Mod {
+            pub fn from_name(name: &str) -> Self {
+                match name {
+                    $($name => Mod::$variant,)*
+                    other => Mod::Unknown(other.to_string()),
+                }
+            }
+
+            pub fn name(&self) -> &str {
+                match self {
+                    $(Mod::$variant => $name,)*
+                    Mod::Unknown(name) => name,
+                }
+            }
+        }
+    };
+}
+
+mods! {
+    // Player mods
+    Acidic => "ACIDIC",
+    AffinityForCrows => "AFFINITY_FOR_CROWS",
+    Alternate => "ALTERNATE",
+    Ambitious => "AMBITIOUS",
+    Attractor => "ATTRACTOR",
+    BaseInstincts => "BASE_INSTINCTS",
+    BirdSeed => "BIRD_SEED",
+    Blaserunning => "BLASERUNNING",
+    Careful => "CAREFUL",
+    Chunky => "CHUNKY",
+    Coasting => "COASTING",
+    CoffeeExit => "COFFEE_EXIT",
+    CoffeePeril => "COFFEE_PERIL",
+    CoffeeRally => "COFFEE_RALLY",
+    CreditToTheTeam => "CREDIT_TO_THE_TEAM",
+    CurseOfCrows => "CURSE_OF_CROWS",
+    Debt => "DEBT",
+    DebtThree => "DEBT_THREE",
+    DebtZero => "DEBT_ZERO",
+    DoublePayouts => "DOUBLE_PAYOUTS",
+    Echo => "ECHO",
+    Ego1 => "EGO1",
+    Ego2 => "EGO2",
+    Ego3 => "EGO3",
+    Ego4 => "EGO4",
+    Elsewhere => "ELSEWHERE",
+    Fiery => "FIERY",
+    FireEater => "FIRE_EATER",
+    FireProtector => "FIRE_PROTECTOR",
+    Fireproof => "FIREPROOF",
+    Flickering => "FLICKERING",
+    Flinch => "FLINCH",
+    Force => "FORCE",
+    FreeRefill => "FREE_REFILL",
+    FriendOfCrows => "FRIEND_OF_CROWS",
+    Gravity => "GRAVITY",
+    Growth => "GROWTH",
+    HardBoiled => "HARD_BOILED",
+    Haunted => "HAUNTED",
+    HeatingUp => "HEATING_UP",
+    HighPressure => "HIGH_PRESSURE",
+    Homebody => "HOMEBODY",
+    HoneyRoasted => "HONEY_ROASTED",
+    Immaterial => "IMMATERIAL",
+    Inhabiting => "INHABITING",
+    LateToParty => "LATE_TO_PARTY",
+    Legendary => "LEGENDARY",
+    Liberated => "LIBERATED",
+    LifeOfParty => "LIFE_OF_PARTY",
+    Love => "LOVE",
+    Magmatic => "MAGMATIC",
+    MaintenanceMode => "MAINTENANCE_MODE",
+    Marked => "MARKED",
+    Maximalist => "MAXIMALIST",
+    Middling => "MIDDLING",
+    Mild => "MILD",
+    Minimalist => "MINIMALIST",
+    MindTrick => "MIND_TRICK",
+    Negative => "NEGATIVE",
+    NightVision => "NIGHT_VISION",
+    NonIdolized => "NON_IDOLIZED",
+    Observed => "OBSERVED",
+    ONo => "O_NO",
+    OnFire => "ON_FIRE",
+    Overperforming => "OVERPERFORMING",
+    OverUnder => "OVERUNDER",
+    Parasite => "PARASITE",
+    Perk => "PERK",
+    Positive => "POSITIVE",
+    ProSkater => "PRO_SKATER",
+    Psychic => "PSYCHIC",
+    Receiver => "RECEIVER",
+    Redacted => "REDACTED",
+    RedHot => "RED_HOT",
+    Repeating => "REPEATING",
+    Replica => "REPLICA",
+    Retired => "RETIRED",
+    Returned => "RETURNED",
+    Reverberating => "REVERBERATING",
+    Scattered => "SCATTERED",
+    Seeker => "SEEKER",
+    Shelled => "SHELLED",
+    Siphon => "SIPHON",
+    Skipping => "SKIPPING",
+    SlowBuild => "SLOW_BUILD",
+    Smooth => "SMOOTH",
+    Soundproof => "SOUNDPROOF",
+    Spicy => "SPICY",
+    Squiddish => "SQUIDDISH",
+    Stable => "STABLE",
+    Static => "STATIC",
+    Subtractor => "SUBTRACTOR",
+    Superallergic => "SUPERALLERGIC",
+    Superyummy => "SUPERYUMMY",
+    SwimBladder => "SWIM_BLADDER",
+    Tired => "TIRED",
+    Trader => "TRADER",
+    Traitor => "TRAITOR",
+    Traveling => "TRAVELING",
+    TripleThreat => "TRIPLE_THREAT",
+    Uncertain => "UNCERTAIN",
+    Undefined => "UNDEFINED",
+    UnderOver => "UNDEROVER",
+    Underhanded => "UNDERHANDED",
+    Underperforming => "UNDERPERFORMING",
+    Undertaker => "UNDERTAKER",
+    Unstable => "UNSTABLE",
+    Wanderer => "WANDERER",
+    Wild => "WILD",
+    Wired => "WIRED",
+    Yolked => "YOLKED",
+    Zero => "ZERO",
+
+    // Team mods
+    Aa => "AA",
+    Aaa => "AAA",
+    BottomDweller => "BOTTOM_DWELLER",
+    Carcinization => "CARCINIZATION",
+    Earlbirds => "EARLBIRDS",
+    Electric => "ELECTRIC",
+    ExtraStrike => "EXTRA_STRIKE",
+    FifthBase => "FIFTH_BASE",
+    FourthStrike => "FOURTH_STRIKE",
+    FreeWill => "FREE_WILL",
+    GoodRiddance => "GOOD_RIDDANCE",
+    H2O => "H20",
+    HomeField => "HOME_FIELD",
+    PartyTime => "PARTY_TIME",
+    PopcornPayouts => "POPCORN_PAYOUTS",
+    Psychoacoustics => "PSYCHOACOUSTICS",
+    Sealant => "SEALANT",
+    ShameGiver => "SHAME_GIVER",
+    SinkingShip => "SINKING_SHIP",
+    StalepopcornPayouts => "STALEPOPCORN_PAYOUTS",
+    SunDialed => "SUN_DIALED",
+    Undersea => "UNDERSEA",
+    Unholey => "UNHOLEY",
+    WalkInThePark => "WALK_IN_THE_PARK",
+
+    // Stadium mods
+    BigBuckets => "BIG_BUCKETS",
+    BirdHotel => "BIRD_HOTEL",
+    EchoChamber => "ECHO_CHAMBER",
+    EventHorizon => "EVENT_HORIZON",
+    FaxMachine => "FAX_MACHINE",
+    FloodPumps => "FLOOD_PUMPS",
+    GrindRail => "GRIND_RAIL",
+    Hoops => "HOOPS",
+    HotelMotel => "HOTEL_MOTEL",
+    LightSwitch => "LIGHT_SWITCH",
+    PeanutMister => "PEANUT_MISTER",
+    SalmonCannons => "SALMON_CANNONS",
+    SecretBase => "SECRET_BASE",
+    Smithy => "SMITHY",
+    SolarPanels => "SOLAR_PANELS",
+    Sweetener => "SWEETENER",
+    ThievesGuild => "THIEVES_GUILD",
+    Tunnels => "TUNNELS",
+    Turntables => "TURNTABLES",
+    Voicemail => "VOICEMAIL",
+}
+
+impl Display for Mod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// Mods are (de)serialized as their Blaseball names, which also lets unknown mods survive a round
+// trip through the world store
+impl Serialize for Mod {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Mod {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Ok(Mod::from_name(&name))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModLayer {
+    Perm,
+    Seas,
+    Week,
+    Game,
+    Item,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ModOwner {
+    Player,
+    Team,
+    Stadium,
+    League,
+}
+
+// Where an effective mod came from
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ModSource {
+    pub owner: ModOwner,
+    pub layer: ModLayer,
+    // For item mods, the item that granted it
+    pub item_id: Option<Uuid>,
+}
@@ -0,0 +1,229 @@
+// Checks every version of every team and player in the league during a time range against the
+// structs in chronicler_schema, so fields Chronicler added (or stopped filling in) show up before they break a
+// world load. Run with `schema-drift <start> <end>`.
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use serde_json::Value;
+use uuid::Uuid;
+use crate::chron::ChronClient;
+use crate::chronicler_schema::{Item, ItemAdjustment, ItemElement, Player, PlayerState, Team, TeamState};
+use crate::world_store::WorldStore;
+
+// A deserializer that only exists to be handed to a struct's Deserialize impl, which tells it the
+// names of the struct's fields (after renames) and then gives up
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de, 'a> Deserializer<'de> for FieldNames<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("Only structs have field names"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], _visitor: V) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("Got the field names"))
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier ignored_any
+    }
+}
+
+fn field_names<T: DeserializeOwned>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+// One struct in the schema, and where its objects are found in an entity's JSON
+struct Shape {
+    name: &'static str,
+    // The fields to follow from the entity to the objects. Empty for the entity itself. Arrays
+    // along the way are looked into, so ["items", "root"] is the root of every item.
+    path: &'static [&'static str],
+    fields: &'static [&'static str],
+}
+
+impl Shape {
+    fn new<T: DeserializeOwned>(name: &'static str, path: &'static [&'static str]) -> Self {
+        Self { name, path, fields: field_names::<T>() }
+    }
+
+    fn objects<'a>(&self, entity: &'a Value) -> Vec<&'a serde_json::Map<String, Value>> {
+        objects_at(entity, self.path)
+    }
+}
+
+fn objects_at<'a>(value: &'a Value, path: &[&str]) -> Vec<&'a serde_json::Map<String, Value>> {
+    match value {
+        Value::Array(values) => values.iter().flat_map(|value| objects_at(value, path)).collect(),
+        Value::Object(object) => match path.split_first() {
+            None => vec![object],
+            Some((field, rest)) => object.get(*field).map(|value| objects_at(value, rest)).unwrap_or_default(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+fn objects_at_mut<'a>(value: &'a mut Value, path: &[&str]) -> Vec<&'a mut serde_json::Map<String, Value>> {
+    match value {
+        Value::Array(values) => values.iter_mut().flat_map(|value| objects_at_mut(value, path)).collect(),
+        Value::Object(object) => match path.split_first() {
+            None => vec![object],
+            Some((field, rest)) => object.get_mut(*field).map(|value| objects_at_mut(value, rest)).unwrap_or_default(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+// Every place an item element can be, and the adjustments inside each
+const ITEM_ELEMENT_PATHS: [&[&str]; 5] = [
+    &["items", "root"],
+    &["items", "prePrefix"],
+    &["items", "postPrefix"],
+    &["items", "prefixes"],
+    &["items", "suffix"],
+];
+const ITEM_ADJUSTMENT_PATHS: [&[&str]; 5] = [
+    &["items", "root", "adjustments"],
+    &["items", "prePrefix", "adjustments"],
+    &["items", "postPrefix", "adjustments"],
+    &["items", "prefixes", "adjustments"],
+    &["items", "suffix", "adjustments"],
+];
+
+#[derive(Default)]
+struct Occurrences {
+    count: usize,
+    first_seen: Option<DateTime<Utc>>,
+    seasons: BTreeSet<i32>,
+}
+
+impl Occurrences {
+    fn record(&mut self, time: DateTime<Utc>, season: Option<i32>) {
+        self.count += 1;
+        self.first_seen = Some(self.first_seen.map_or(time, |first| first.min(time)));
+        self.seasons.extend(season);
+    }
+
+    fn describe(&self) -> String {
+        // Seasons are zero-indexed internally, but people talk about them one-indexed
+        let seasons = self.seasons.iter().map(|season| format!("S{}", season + 1)).collect::<Vec<_>>();
+        let introduced = self.seasons.first()
+            .map(|season| format!(", introduced in S{}", season + 1))
+            .unwrap_or_default();
+        let first_seen = self.first_seen.map(|time| time.to_rfc3339()).unwrap_or_default();
+        format!("{} versions, first seen {first_seen}{introduced} (seasons: {})", self.count, seasons.join(", "))
+    }
+}
+
+#[derive(Default)]
+struct Report {
+    unknown_fields: BTreeMap<String, Occurrences>,
+    type_mismatches: BTreeMap<String, Occurrences>,
+    // Known fields, by "Struct.field", that we've seen with a value other than null
+    non_null_fields: HashMap<String, bool>,
+}
+
+impl Report {
+    fn check<T: DeserializeOwned>(&mut self, ty: &str, shapes: &[Shape], time: DateTime<Utc>, season: Option<i32>, entity: &Value) {
+        for shape in shapes {
+            for object in shape.objects(entity) {
+                for (field, value) in object {
+                    if !shape.fields.contains(&field.as_str()) {
+                        self.unknown_fields.entry(format!("{}.{field}", shape.name)).or_default().record(time, season);
+                    } else if !value.is_null() {
+                        self.non_null_fields.insert(format!("{}.{field}", shape.name), true);
+                    }
+                }
+            }
+            for field in shape.fields {
+                self.non_null_fields.entry(format!("{}.{field}", shape.name)).or_insert(false);
+            }
+        }
+
+        // Unknown fields are already reported, so take them out to find out whether the rest fits
+        let mut known = entity.clone();
+        for shape in shapes {
+            strip_unknown_fields(&mut known, shape);
+        }
+        if let Err(e) = T::deserialize(&known) {
+            self.type_mismatches.entry(format!("{ty}: {e}")).or_default().record(time, season);
+        }
+    }
+
+    fn print(&self) {
+        println!("Unknown fields:");
+        for (field, occurrences) in &self.unknown_fields {
+            println!("    {field}: {}", occurrences.describe());
+        }
+        println!("Type mismatches:");
+        for (error, occurrences) in &self.type_mismatches {
+            println!("    {error}: {}", occurrences.describe());
+        }
+        println!("Fields that are always null or missing:");
+        for field in self.non_null_fields.iter().filter(|(_, &seen)| !seen).map(|(field, _)| field).sorted() {
+            println!("    {field}");
+        }
+    }
+}
+
+fn strip_unknown_fields(entity: &mut Value, shape: &Shape) {
+    for object in objects_at_mut(entity, shape.path) {
+        object.retain(|field, _| shape.fields.contains(&field.as_str()));
+    }
+}
+
+// The season in effect at `time`, from the sim versions (sorted by time)
+fn season_at(sims: &[(DateTime<Utc>, Option<i32>)], time: DateTime<Utc>) -> Option<i32> {
+    sims.iter()
+        .take_while(|(valid_from, _)| *valid_from <= time)
+        .last()
+        .and_then(|(_, season)| *season)
+}
+
+pub async fn run(store: &WorldStore, client: &ChronClient, start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<()> {
+    store.populate(client, start, end).await?;
+
+    let mut sims = store.raw_versions_in("sim", &[Uuid::nil()], start, end)?.into_iter()
+        .map(|(time, sim)| (time, sim.get("season").and_then(Value::as_i64).map(|season| season as i32)))
+        .collect::<Vec<_>>();
+    sims.sort_by_key(|(time, _)| *time);
+
+    let team_shapes = [
+        Shape::new::<Team>("Team", &[]),
+        Shape::new::<TeamState>("TeamState", &["state"]),
+    ];
+    let mut player_shapes = vec![
+        Shape::new::<Player>("Player", &[]),
+        Shape::new::<PlayerState>("PlayerState", &["state"]),
+    ];
+    player_shapes.push(Shape::new::<Item>("Item", &["items"]));
+    player_shapes.extend(ITEM_ELEMENT_PATHS.into_iter().map(|path| Shape::new::<ItemElement>("ItemElement", path)));
+    player_shapes.extend(ITEM_ADJUSTMENT_PATHS.into_iter().map(|path| Shape::new::<ItemAdjustment>("ItemAdjustment", path)));
+
+    // Only entities in the league during the window, the same ones populate() fetched. The store
+    // can hold others from earlier windows.
+    let team_ids = store.team_ids_in(start, end)?;
+    let player_ids = store.player_ids_in(&team_ids, start, end)?;
+
+    let mut report = Report::default();
+    let teams = store.raw_versions_in("team", &team_ids, start, end)?;
+    println!("Checking {} team versions", teams.len());
+    for (time, team) in &teams {
+        report.check::<Team>("team", &team_shapes, *time, season_at(&sims, *time), team);
+    }
+    let players = store.raw_versions_in("player", &player_ids, start, end)?;
+    println!("Checking {} player versions", players.len());
+    for (time, player) in &players {
+        report.check::<Player>("player", &player_shapes, *time, season_at(&sims, *time), player);
+    }
+
+    report.print();
+    Ok(())
+}
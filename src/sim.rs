@@ -1,20 +1,71 @@
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use anyhow::anyhow;
+use chrono::{DateTime, Utc};
 use fed::{FedEvent, FedEventData};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
-use crate::chronicler_schema::{Player, Team};
+use crate::chronicler_schema::{Player, Stadium, Team};
 use crate::game::Game;
+use crate::mods::{Mod, ModLayer, ModOwner, ModSource};
 use crate::rng::Rng;
 
+// The parts of the sim entity we care about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimState {
+    pub season: i32,
+    pub day: i32,
+    pub phase: i32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct World {
+    // None when the data doesn't include the sim, like dumps without the league structure
+    pub sim: Option<SimState>,
     pub teams: HashMap<Uuid, Team>,
+    // Includes everyone on a team: lineup, rotation, shadows, and bench and bullpen for the seasons
+    // that have them. Items come along with the players that hold them.
     pub players: HashMap<Uuid, Player>,
+    pub stadiums: HashMap<Uuid, Stadium>,
+    pub league_mods: Vec<Mod>,
+    // Players who join the league partway through, as they were when they joined. They tell the
+    // sim that someone new is coming, and move into `players` when Chronicler's version of them is
+    // applied.
+    pub newcomers: HashMap<Uuid, Player>,
+}
+
+// A new version of some entity, from Chronicler
+#[derive(Debug)]
+pub enum WorldChange {
+    Sim { sim: SimState, league_mods: Vec<Mod> },
+    Team(Team),
+    Player(Player),
+    Stadium(Stadium),
+}
+
+#[derive(Debug)]
+pub struct WorldUpdate {
+    pub valid_from: DateTime<Utc>,
+    pub change: WorldChange,
 }
 
 impl World {
+    // Replaces whatever the world had for this entity, including anything the sim changed about it
+    pub fn apply(&mut self, change: WorldChange) {
+        match change {
+            WorldChange::Sim { sim, league_mods } => {
+                self.sim = Some(sim);
+                self.league_mods = league_mods;
+            }
+            WorldChange::Team(team) => { self.teams.insert(team.id, team); }
+            WorldChange::Player(player) => {
+                self.newcomers.remove(&player.id);
+                self.players.insert(player.id, player);
+            }
+            WorldChange::Stadium(stadium) => { self.stadiums.insert(stadium.id, stadium); }
+        }
+    }
+
     // pretty sure self and player_ids could have different lifetimes if needed
     pub fn iter_players<'a>(&'a self, player_ids: &'a [Uuid]) -> impl Iterator<Item=Option<&'a Player>> + 'a {
         player_ids.into_iter()
@@ -27,16 +78,73 @@ impl World {
             .chain(self.iter_players(&team.rotation)))
     }
 
-    pub fn any_player_on_team_has_mod(&self, team_id: Uuid, mod_name: &str) -> anyhow::Result<bool> {
+    pub fn shadows_on_team(&self, team_id: Uuid) -> Option<impl Iterator<Item=Option<&Player>>> {
+        let team = self.teams.get(&team_id)?;
+        Some(self.iter_players(team.shadows.as_deref().unwrap_or_default()))
+    }
+
+    pub fn any_player_on_team_has_mod(&self, team_id: Uuid, m: Mod, stadium_id: Option<Uuid>) -> anyhow::Result<bool> {
         let players = self.players_on_team(team_id)
             .ok_or_else(|| anyhow!("Couldn't find batting team"))?;
         for player in players {
             let player = player.ok_or_else(|| anyhow!("Couldn't find player from team rotation or lineup"))?;
-            if player.has_mod(mod_name) { return Ok(true) }
+            if self.player_has_mod(player, m.clone(), stadium_id) { return Ok(true) }
         }
 
         Ok(false)
     }
+
+    // Whether the player effectively has this mod, and if so where it came from. The player's own
+    // mods take precedence, then their team's, then the stadium of the game they're playing in
+    // (which is the other team's for away players), then the league's. Outside a game, pass None
+    // and there's no stadium layer.
+    pub fn mod_source(&self, player: &Player, m: Mod, stadium_id: Option<Uuid>) -> Option<ModSource> {
+        if let Some(layer) = player.mod_layer(m.clone()) {
+            let item_id = if layer == ModLayer::Item {
+                player.item_granting_mod(&m).map(|item| item.id)
+            } else {
+                None
+            };
+            return Some(ModSource { owner: ModOwner::Player, layer, item_id });
+        }
+
+        let team = player.league_team_id.and_then(|team_id| self.teams.get(&team_id));
+        if let Some(layer) = team.and_then(|team| team.mod_layer(m.clone())) {
+            return Some(ModSource { owner: ModOwner::Team, layer, item_id: None });
+        }
+
+        let stadium = stadium_id.and_then(|stadium_id| self.stadiums.get(&stadium_id));
+        if stadium.is_some_and(|stadium| stadium.has_mod(m.clone())) {
+            return Some(ModSource { owner: ModOwner::Stadium, layer: ModLayer::Perm, item_id: None });
+        }
+
+        if self.league_mods.contains(&m) {
+            return Some(ModSource { owner: ModOwner::League, layer: ModLayer::Perm, item_id: None });
+        }
+
+        None
+    }
+
+    pub fn player_has_mod(&self, player: &Player, m: Mod, stadium_id: Option<Uuid>) -> bool {
+        self.mod_source(player, m, stadium_id).is_some()
+    }
+
+    // Human-readable descriptions of every mod we couldn't parse
+    pub fn unknown_mods(&self) -> Vec<String> {
+        let team_mods = self.teams.values()
+            .flat_map(|team| team.unknown_mods().map(move |name| format!("{name} on {team}")));
+        let player_mods = self.players.values()
+            .flat_map(|player| player.unknown_mods().map(move |name| format!("{name} on {player}")));
+        let stadium_mods = self.stadiums.values()
+            .flat_map(|stadium| stadium.unknown_mods().map(move |name| format!("{name} on {stadium}")));
+        let league_mods = self.league_mods.iter()
+            .filter_map(|m| match m {
+                Mod::Unknown(name) => Some(format!("{name} on the league")),
+                _ => None,
+            });
+
+        team_mods.chain(player_mods).chain(stadium_mods).chain(league_mods).collect()
+    }
 }
 
 #[derive(Debug)]
@@ -44,6 +152,10 @@ pub struct Sim {
     games: HashMap<Uuid, Game>,
     world: World,
     rng: Rng,
+    // Chronicler's versions of the world, applied as the events catch up to them. Empty unless the
+    // sim was built with with_updates(), in which case the world only changes the way the sim
+    // changes it.
+    updates: VecDeque<WorldUpdate>,
 }
 
 impl Sim {
@@ -52,17 +164,32 @@ impl Sim {
             games: Default::default(),
             world,
             rng: Rng::new(s0, s1),
+            updates: VecDeque::new(),
+        }
+    }
+
+    pub fn with_updates(mut self, updates: Vec<WorldUpdate>) -> Self {
+        self.updates = updates.into();
+        self
+    }
+
+    fn apply_updates_until(&mut self, time: DateTime<Utc>) {
+        while self.updates.front().is_some_and(|update| update.valid_from <= time) {
+            let update = self.updates.pop_front().unwrap();
+            self.world.apply(update.change);
         }
     }
 
     pub fn check_next_event(&mut self, event: &FedEvent) -> anyhow::Result<()> {
+        self.apply_updates_until(event.created);
+
         let Some(game_event) = event.data.game() else {
             return Ok(())
         };
         let game = match self.games.entry(game_event.game_id) {
             Entry::Occupied(entry) => { entry.into_mut() }
             Entry::Vacant(entry) => if let FedEventData::LetsGo { game, weather, stadium_id } = &event.data {
-                entry.insert(Game::new(game, *weather, *stadium_id))
+                entry.insert(Game::new(game, *weather, *stadium_id, event.season, event.day))
             } else {
                 return Err(anyhow!("First event for game was not a LetsGo event"))
             }
@@ -70,7 +197,15 @@ impl Sim {
 
         let event_from_sim = game.tick(&mut self.world, &mut self.rng)?;
 
-        assert_eq!(event.data, event_from_sim);
+        match (&event.data, &event_from_sim) {
+            // Flavor text messages are picked from lists we don't have, so the sim leaves them
+            // empty and only the rest of the event is checked
+            (FedEventData::PeanutFlavorText { game: observed, .. }, FedEventData::PeanutFlavorText { game: simulated, .. }) |
+            (FedEventData::BirdsFlavorText { game: observed, .. }, FedEventData::BirdsFlavorText { game: simulated, .. }) => {
+                assert_eq!(observed, simulated);
+            }
+            _ => assert_eq!(event.data, event_from_sim),
+        }
 
         println!("Validated {} for game {}", event_from_sim.as_ref(), game_event.game_id);
         Ok(())
@@ -0,0 +1,380 @@
+// Effective stats: a player's raw attributes plus item adjustments, with every situational
+// multiplier applied. Formulas should only ever see these, never the raw fields on Player.
+use std::f64::consts::PI;
+use fed::Weather;
+use uuid::Uuid;
+use crate::chronicler_schema::{Player, Stat};
+use crate::mods::Mod;
+use crate::sim::World;
+
+#[derive(Debug, Clone)]
+pub struct StatContext {
+    // Zero-indexed, like everything else
+    pub season: i32,
+    pub day: i32,
+    pub weather: Weather,
+    pub home_team: Uuid,
+    // The stadium the game is being played in, for stadium mods
+    pub stadium_id: Option<Uuid>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StatCategory {
+    Batting,
+    Pitching,
+    Defense,
+    Baserunning,
+}
+
+#[derive(Debug, Clone)]
+pub struct BattingStats {
+    pub buoyancy: f64,
+    pub divinity: f64,
+    pub moxie: f64,
+    pub musclitude: f64,
+    pub patheticism: f64,
+    pub thwackability: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PitchingStats {
+    pub overpowerment: f64,
+    pub ruthlessness: f64,
+    pub suppression: f64,
+    pub unthwackability: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct DefenseStats {
+    pub anticapitalism: f64,
+    pub chasiness: f64,
+    pub omniscience: f64,
+    pub tenaciousness: f64,
+    pub watchfulness: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct BaserunningStats {
+    pub base_thirst: f64,
+    pub continuation: f64,
+    pub ground_friction: f64,
+    pub indulgence: f64,
+    pub laserlikeness: f64,
+}
+
+fn is_coffee(weather: Weather) -> bool {
+    matches!(weather, Weather::Coffee | Weather::Coffee2 | Weather::Coffee3s)
+}
+
+// Vibes oscillate over the season with a period set by buoyancy. Pressurization pulls them down
+// and cinnamon pulls them up.
+pub fn vibes(player: &Player, day: i32) -> f64 {
+    let frequency = 6. + (10. * player.buoyancy).round();
+    let phase = PI * ((2. / frequency) * day as f64 + 0.5);
+    let cinnamon = player.cinnamon.unwrap_or(0.);
+    let range = 0.5 * (player.pressurization + cinnamon);
+    range * phase.sin() - 0.5 * player.pressurization + 0.5 * cinnamon
+}
+
+// The sum of all the mod-based multipliers that apply to this player in this category. 1.0 means no
+// change.
+pub fn multiplier(world: &World, player: &Player, ctx: &StatContext, category: StatCategory) -> f64 {
+    let has_mod = |m| world.player_has_mod(player, m, ctx.stadium_id);
+    let team = player.league_team_id.and_then(|team_id| world.teams.get(&team_id));
+    let is_home = player.league_team_id == Some(ctx.home_team);
+
+    let mut multiplier = 1.;
+    if has_mod(Mod::Overperforming) { multiplier += 0.2; }
+    if has_mod(Mod::Underperforming) { multiplier -= 0.2; }
+    if has_mod(Mod::Perk) && is_coffee(ctx.weather) { multiplier += 0.2; }
+    if has_mod(Mod::Growth) {
+        // Ramps up over the regular season and caps at 5%
+        multiplier += 0.05 * (ctx.day as f64 / 99.).min(1.);
+    }
+    if has_mod(Mod::Traveling) && !is_home {
+        // (s14+) Traveling applies to every category. Before that it only boosted batting
+        if ctx.season >= 13 || category == StatCategory::Batting {
+            multiplier += 0.05;
+        }
+    }
+    if has_mod(Mod::SinkingShip) {
+        // +1% for every player below a full roster of 14 (and -1% for every player above it)
+        if let Some(team) = team {
+            let roster_size = (team.lineup.len() + team.rotation.len()) as f64;
+            multiplier += (14. - roster_size) * 0.01;
+        }
+    }
+    if has_mod(Mod::AffinityForCrows) && ctx.weather == Weather::Birds &&
+        matches!(category, StatCategory::Batting | StatCategory::Pitching) {
+        multiplier += 0.5;
+    }
+    if has_mod(Mod::Undersea) && ctx.weather == Weather::Flooding { multiplier += 0.2; }
+    if has_mod(Mod::Earlbirds) && ctx.day < 27 { multiplier += 0.2; }
+    if has_mod(Mod::LateToParty) && ctx.day >= 72 { multiplier += 0.2; }
+
+    multiplier
+}
+
+struct Effective {
+    multiplier: f64,
+    vibes: f64,
+}
+
+impl Effective {
+    fn new(world: &World, player: &Player, ctx: &StatContext, category: StatCategory) -> Self {
+        Self {
+            multiplier: multiplier(world, player, ctx, category),
+            vibes: vibes(player, ctx.day),
+        }
+    }
+
+    // For stats where higher is better for the player
+    fn positive(&self, raw: f64) -> f64 {
+        raw * self.multiplier * (1. + 0.2 * self.vibes)
+    }
+
+    // For stats where higher is worse for the player, which is just patheticism. Vibes don't
+    // affect it.
+    fn negative(&self, raw: f64) -> f64 {
+        raw / self.multiplier
+    }
+}
+
+pub fn batting(world: &World, player: &Player, ctx: &StatContext) -> BattingStats {
+    let e = Effective::new(world, player, ctx, StatCategory::Batting);
+    BattingStats {
+        buoyancy: e.positive(player.buoyancy + player.item_stat_bonus(Stat::Buoyancy)),
+        divinity: e.positive(player.divinity + player.item_stat_bonus(Stat::Divinity)),
+        moxie: e.positive(player.moxie + player.item_stat_bonus(Stat::Moxie)),
+        musclitude: e.positive(player.musclitude + player.item_stat_bonus(Stat::Musclitude)),
+        patheticism: e.negative(player.patheticism + player.item_stat_bonus(Stat::Patheticism)),
+        thwackability: e.positive(player.thwackability + player.item_stat_bonus(Stat::Thwackability)),
+    }
+}
+
+pub fn pitching(world: &World, player: &Player, ctx: &StatContext) -> PitchingStats {
+    let e = Effective::new(world, player, ctx, StatCategory::Pitching);
+    PitchingStats {
+        overpowerment: e.positive(player.overpowerment + player.item_stat_bonus(Stat::Overpowerment)),
+        ruthlessness: e.positive(player.ruthlessness + player.item_stat_bonus(Stat::Ruthlessness)),
+        suppression: e.positive(player.suppression + player.item_stat_bonus(Stat::Suppression)),
+        unthwackability: e.positive(player.unthwackability + player.item_stat_bonus(Stat::Unthwackability)),
+    }
+}
+
+pub fn defense(world: &World, player: &Player, ctx: &StatContext) -> DefenseStats {
+    let e = Effective::new(world, player, ctx, StatCategory::Defense);
+    DefenseStats {
+        anticapitalism: e.positive(player.anticapitalism + player.item_stat_bonus(Stat::Anticapitalism)),
+        chasiness: e.positive(player.chasiness + player.item_stat_bonus(Stat::Chasiness)),
+        omniscience: e.positive(player.omniscience + player.item_stat_bonus(Stat::Omniscience)),
+        tenaciousness: e.positive(player.tenaciousness + player.item_stat_bonus(Stat::Tenaciousness)),
+        watchfulness: e.positive(player.watchfulness + player.item_stat_bonus(Stat::Watchfulness)),
+    }
+}
+
+pub fn baserunning(world: &World, player: &Player, ctx: &StatContext) -> BaserunningStats {
+    let e = Effective::new(world, player, ctx, StatCategory::Baserunning);
+    BaserunningStats {
+        base_thirst: e.positive(player.base_thirst + player.item_stat_bonus(Stat::BaseThirst)),
+        continuation: e.positive(player.continuation + player.item_stat_bonus(Stat::Continuation)),
+        ground_friction: e.positive(player.ground_friction + player.item_stat_bonus(Stat::GroundFriction)),
+        indulgence: e.positive(player.indulgence + player.item_stat_bonus(Stat::Indulgence)),
+        laserlikeness: e.positive(player.laserlikeness + player.item_stat_bonus(Stat::Laserlikeness)),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use serde_json::json;
+    use crate::chronicler_schema::Team;
+    use super::*;
+
+    const HOME_TEAM: Uuid = Uuid::from_u128(1);
+    const AWAY_TEAM: Uuid = Uuid::from_u128(2);
+    const STATS: [&str; 25] = [
+        "buoyancy", "coldness", "chasiness", "divinity", "martyrdom", "baseThirst", "indulgence",
+        "musclitude", "tragicness", "omniscience", "patheticism", "suppression", "continuation",
+        "ruthlessness", "watchfulness", "laserlikeness", "overpowerment", "tenaciousness",
+        "thwackability", "anticapitalism", "groundFriction", "pressurization", "unthwackability",
+        "shakespearianism", "moxie",
+    ];
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {expected}, got {actual}");
+    }
+
+    // Every stat at 0.5, and no pressurization or cinnamon so vibes are always zero
+    pub(crate) fn player(team_id: Uuid, mods: Vec<Mod>) -> Player {
+        let mut player = json!({
+            "id": Uuid::new_v4(), "name": "Test Player", "soul": 5, "leagueTeamId": team_id,
+            "permAttr": mods, "cinnamon": 0., "totalFingers": 10,
+        });
+        for stat in STATS {
+            player[stat] = json!(0.5);
+        }
+        player["pressurization"] = json!(0.);
+        serde_json::from_value(player).unwrap()
+    }
+
+    pub(crate) fn team(id: Uuid, lineup_len: usize, rotation_len: usize, mods: Vec<Mod>) -> Team {
+        let ids = |len| (0..len).map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+        serde_json::from_value(json!({
+            "id": id, "emoji": "", "lineup": ids(lineup_len), "slogan": "", "fullName": "Test Team",
+            "gameAttr": [], "location": "", "nickname": "", "permAttr": mods,
+            "rotation": ids(rotation_len), "seasAttr": [], "weekAttr": [], "mainColor": "",
+            "shameRuns": 0, "shorthand": "", "teamSpirit": 0, "totalShames": 0, "rotationSlot": 0,
+            "seasonShames": 0, "championships": 0, "totalShamings": 0, "seasonShamings": 0,
+            "secondaryColor": "",
+        })).unwrap()
+    }
+
+    pub(crate) fn world(teams: Vec<Team>) -> World {
+        let mut world: World = serde_json::from_value(json!({
+            "sim": { "season": 13, "day": 0, "phase": 2 },
+            "teams": {}, "players": {}, "stadiums": {}, "league_mods": [], "newcomers": {},
+        })).unwrap();
+        world.teams = teams.into_iter().map(|team| (team.id, team)).collect();
+        world
+    }
+    fn ctx(season: i32, day: i32, weather: Weather) -> StatContext {
+        StatContext { season, day, weather, home_team: HOME_TEAM, stadium_id: None }
+    }
+
+    fn home_multiplier(mods: Vec<Mod>, ctx: &StatContext, category: StatCategory) -> f64 {
+        let world = world(vec![team(HOME_TEAM, 9, 5, Vec::new())]);
+        multiplier(&world, &player(HOME_TEAM, mods), ctx, category)
+    }
+
+    #[test]
+    fn no_mods_means_no_change() {
+        assert_close(home_multiplier(vec![], &ctx(13, 10, Weather::Sun2), StatCategory::Batting), 1.);
+    }
+
+    #[test]
+    fn overperforming_and_underperforming() {
+        let ctx = ctx(13, 10, Weather::Sun2);
+        assert_close(home_multiplier(vec![Mod::Overperforming], &ctx, StatCategory::Pitching), 1.2);
+        assert_close(home_multiplier(vec![Mod::Underperforming], &ctx, StatCategory::Pitching), 0.8);
+        assert_close(home_multiplier(vec![Mod::Overperforming, Mod::Underperforming], &ctx, StatCategory::Pitching), 1.);
+    }
+
+    #[test]
+    fn perk_only_in_coffee_weather() {
+        for weather in [Weather::Coffee, Weather::Coffee2, Weather::Coffee3s] {
+            assert_close(home_multiplier(vec![Mod::Perk], &ctx(13, 10, weather), StatCategory::Batting), 1.2);
+        }
+        assert_close(home_multiplier(vec![Mod::Perk], &ctx(13, 10, Weather::Sun2), StatCategory::Batting), 1.);
+    }
+
+    #[test]
+    fn growth_ramps_up_and_caps() {
+        let growth = |day| home_multiplier(vec![Mod::Growth], &ctx(13, day, Weather::Sun2), StatCategory::Defense);
+        assert_close(growth(0), 1.);
+        assert_close(growth(33), 1. + 0.05 / 3.);
+        assert_close(growth(99), 1.05);
+        // Postseason days don't keep growing
+        assert_close(growth(110), 1.05);
+    }
+
+    #[test]
+    fn traveling_before_and_after_season_14() {
+        let world = world(vec![team(HOME_TEAM, 9, 5, Vec::new()), team(AWAY_TEAM, 9, 5, Vec::new())]);
+        let away = player(AWAY_TEAM, vec![Mod::Traveling]);
+        let home = player(HOME_TEAM, vec![Mod::Traveling]);
+
+        let s13 = ctx(12, 10, Weather::Sun2);
+        assert_close(multiplier(&world, &away, &s13, StatCategory::Batting), 1.05);
+        assert_close(multiplier(&world, &away, &s13, StatCategory::Pitching), 1.);
+
+        let s14 = ctx(13, 10, Weather::Sun2);
+        for category in [StatCategory::Batting, StatCategory::Pitching, StatCategory::Defense, StatCategory::Baserunning] {
+            assert_close(multiplier(&world, &away, &s14, category), 1.05);
+            assert_close(multiplier(&world, &home, &s14, category), 1.);
+        }
+    }
+
+    #[test]
+    fn sinking_ship_counts_roster_size() {
+        let ctx = ctx(13, 10, Weather::Sun2);
+        let sinking = |lineup_len, rotation_len| {
+            let world = world(vec![team(HOME_TEAM, lineup_len, rotation_len, vec![Mod::SinkingShip])]);
+            multiplier(&world, &player(HOME_TEAM, vec![]), &ctx, StatCategory::Batting)
+        };
+        assert_close(sinking(9, 5), 1.);
+        assert_close(sinking(8, 4), 1.02);
+        assert_close(sinking(10, 6), 0.98);
+    }
+
+    #[test]
+    fn affinity_for_crows_in_birds_weather() {
+        let birds = ctx(13, 10, Weather::Birds);
+        assert_close(home_multiplier(vec![Mod::AffinityForCrows], &birds, StatCategory::Batting), 1.5);
+        assert_close(home_multiplier(vec![Mod::AffinityForCrows], &birds, StatCategory::Pitching), 1.5);
+        assert_close(home_multiplier(vec![Mod::AffinityForCrows], &birds, StatCategory::Defense), 1.);
+        assert_close(home_multiplier(vec![Mod::AffinityForCrows], &ctx(13, 10, Weather::Sun2), StatCategory::Batting), 1.);
+    }
+
+    #[test]
+    fn earlbirds_and_late_to_party() {
+        let on_day = |m: Mod, day| home_multiplier(vec![m], &ctx(13, day, Weather::Sun2), StatCategory::Baserunning);
+        assert_close(on_day(Mod::Earlbirds, 0), 1.2);
+        assert_close(on_day(Mod::Earlbirds, 26), 1.2);
+        assert_close(on_day(Mod::Earlbirds, 27), 1.);
+        assert_close(on_day(Mod::LateToParty, 71), 1.);
+        assert_close(on_day(Mod::LateToParty, 72), 1.2);
+        assert_close(on_day(Mod::LateToParty, 98), 1.2);
+    }
+
+    #[test]
+    fn vibes_follow_pressurization_and_cinnamon() {
+        let mut player = player(HOME_TEAM, vec![]);
+        for day in 0..99 {
+            assert_close(vibes(&player, day), 0.);
+        }
+
+        // The cycle peaks on day 0, where full cinnamon gives the best possible vibes and full
+        // pressurization cancels out to nothing
+        player.cinnamon = Some(1.);
+        assert_close(vibes(&player, 0), 1.);
+        player.cinnamon = Some(0.);
+        player.pressurization = 1.;
+        assert_close(vibes(&player, 0), 0.);
+        for day in 0..99 {
+            let vibes = vibes(&player, day);
+            assert!((-1. ..=0.).contains(&vibes), "vibes on day {day} were {vibes}");
+        }
+
+        // Buoyancy sets the length of the cycle, which is 6 + round(10 * buoyancy) days
+        player.buoyancy = 0.;
+        assert_close(vibes(&player, 3), -1.);
+        assert_close(vibes(&player, 6), 0.);
+    }
+
+    #[test]
+    fn effective_stats_apply_the_multiplier() {
+        let world = world(vec![team(HOME_TEAM, 9, 5, Vec::new())]);
+        let player = player(HOME_TEAM, vec![Mod::Overperforming]);
+        let batting = batting(&world, &player, &ctx(13, 10, Weather::Sun2));
+        assert_close(batting.musclitude, 0.6);
+        // Higher patheticism is worse, so overperforming brings it down
+        assert_close(batting.patheticism, 0.5 / 1.2);
+    }
+
+    #[test]
+    fn item_adjustments_add_to_the_raw_stat() {
+        let world = world(vec![team(HOME_TEAM, 9, 5, Vec::new())]);
+        let mut player = player(HOME_TEAM, vec![]);
+        player.items = Some(vec![serde_json::from_value(json!({
+            "id": Uuid::new_v4(), "name": "Test Bat", "durability": 2, "health": 2,
+            "root": { "name": "Bat", "adjustments": [{ "type": 1, "stat": Stat::Musclitude as usize, "value": 0.1 }] },
+        })).unwrap()]);
+        let stats = batting(&world, &player, &ctx(13, 10, Weather::Sun2));
+        assert_close(stats.musclitude, 0.6);
+        assert_close(stats.divinity, 0.5);
+
+        // Broken items don't count
+        player.items.as_mut().unwrap()[0].health = 0;
+        assert_close(batting(&world, &player, &ctx(13, 10, Weather::Sun2)).musclitude, 0.5);
+    }
+}
@@ -0,0 +1,594 @@
+// Local store of Chronicler entity versions, kept in sled. Versions are keyed by entity id and the
+// time they became valid, so a World can be materialized for any time the store covers. Entities
+// are stored as the JSON Chronicler sent, which means schema changes don't invalidate the store.
+//
+// The store is also what keeps cached worlds trustworthy. A store written with a different layout
+// is cleared and refetched rather than misread, and a window is only recorded as covered once
+// every version in it is on disk, so a crash mid-fetch means a refetch and never a partial world.
+use std::collections::HashMap;
+use std::ops::Bound;
+use anyhow::anyhow;
+use chrono::{DateTime, TimeZone, Utc};
+use itertools::Itertools;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use uuid::Uuid;
+use crate::chron::ChronClient;
+use crate::chronicler_schema::{Player, Stadium, Team};
+use crate::mods::Mod;
+use crate::sim::{SimState, World, WorldChange, WorldUpdate};
+
+// Bump this when the layout of the store changes. Mismatched stores are cleared.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Deserialize)]
+struct SimData {
+    league: Uuid,
+    season: i32,
+    day: i32,
+    phase: i32,
+    // League-wide mods. Most of the era doesn't have any
+    #[serde(default)]
+    attr: Vec<Mod>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LeagueData {
+    subleagues: [Uuid; 2],
+}
+
+#[derive(Debug, Deserialize)]
+struct SubleagueData {
+    divisions: [Uuid; 2],
+}
+
+#[derive(Debug, Deserialize)]
+struct DivisionData {
+    teams: Vec<Uuid>,
+}
+
+// Just the parts of a team needed to find its players and stadium. Unlike Team, this doesn't deny
+// unknown fields, so a new field Chronicler adds can't stop the store from fetching everyone, and
+// schema-drift can still get as far as reporting it.
+#[derive(Debug, Deserialize)]
+struct TeamRoster {
+    lineup: Vec<Uuid>,
+    rotation: Vec<Uuid>,
+    shadows: Option<Vec<Uuid>>,
+    bench: Option<Vec<Uuid>>,
+    bullpen: Option<Vec<Uuid>>,
+    stadium: Option<Uuid>,
+}
+
+impl TeamRoster {
+    fn all_player_ids(&self) -> impl Iterator<Item=&Uuid> {
+        self.lineup.iter()
+            .chain(self.rotation.iter())
+            .chain(self.shadows.iter().flatten())
+            .chain(self.bench.iter().flatten())
+            .chain(self.bullpen.iter().flatten())
+    }
+}
+
+// Sorts the same way as the timestamp does, so sled's byte ordering is time ordering
+fn time_key(time: DateTime<Utc>) -> [u8; 8] {
+    ((time.timestamp_millis() as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+fn time_from_key(key: &[u8]) -> anyhow::Result<DateTime<Utc>> {
+    let bytes: [u8; 8] = key.try_into()?;
+    let millis = (u64::from_be_bytes(bytes) ^ (1 << 63)) as i64;
+    Utc.timestamp_millis_opt(millis).single()
+        .ok_or_else(|| anyhow!("Invalid timestamp in world store key"))
+}
+
+fn version_key(id: Uuid, valid_from: DateTime<Utc>) -> Vec<u8> {
+    id.as_bytes().iter().copied().chain(time_key(valid_from)).collect()
+}
+
+pub struct WorldStore {
+    db: sled::Db,
+}
+
+impl WorldStore {
+    pub fn open(path: &str) -> anyhow::Result<Self> {
+        let db = sled::open(path)?;
+        let meta = db.open_tree("meta")?;
+        let format_version = meta.get("format_version")?
+            .map(|bytes| bincode::deserialize::<u32>(&bytes))
+            .transpose()?;
+        if format_version != Some(FORMAT_VERSION) {
+            if format_version.is_some() {
+                println!("World store has format {format_version:?}, but this build expects {FORMAT_VERSION}. Clearing it");
+            }
+            for name in db.tree_names() {
+                // The default tree can't be dropped, but it's never used
+                if name != db.name() {
+                    db.drop_tree(name)?;
+                }
+            }
+            db.open_tree("meta")?.insert("format_version", bincode::serialize(&FORMAT_VERSION)?)?;
+        }
+        Ok(Self { db })
+    }
+
+    fn versions_tree(&self, ty: &str) -> anyhow::Result<sled::Tree> {
+        Ok(self.db.open_tree(format!("versions/{ty}"))?)
+    }
+
+    // The time ranges, as (start, end) millis, that we've fetched every version for
+    fn coverage(&self, ty: &str, id: Uuid) -> anyhow::Result<Vec<(i64, i64)>> {
+        let tree = self.db.open_tree("coverage")?;
+        Ok(tree.get(format!("{ty}/{id}"))?
+            .map(|bytes| bincode::deserialize(&bytes))
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    fn is_covered(&self, ty: &str, id: Uuid, start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<bool> {
+        let (start, end) = (start.timestamp_millis(), end.timestamp_millis());
+        Ok(self.coverage(ty, id)?.into_iter()
+            .any(|(covered_start, covered_end)| covered_start <= start && end <= covered_end))
+    }
+
+    fn add_coverage(&self, ty: &str, id: Uuid, start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<()> {
+        let mut coverage = self.coverage(ty, id)?;
+        coverage.push((start.timestamp_millis(), end.timestamp_millis()));
+        self.db.open_tree("coverage")?.insert(format!("{ty}/{id}"), bincode::serialize(&coverage)?)?;
+        Ok(())
+    }
+
+    // Makes sure the store has every version of these entities that was valid at any point in
+    // [start, end), fetching whatever it doesn't already have
+    pub async fn ensure_versions(&self, client: &ChronClient, ty: &str, ids: &[Uuid], start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<()> {
+        let mut missing = Vec::new();
+        for &id in ids {
+            if !self.is_covered(ty, id, start, end)? {
+                missing.push(id);
+            }
+        }
+        if missing.is_empty() { return Ok(()); }
+
+        println!("Fetching {} {ty} histories", missing.len());
+        let (initial, changes) = tokio::try_join!(
+            client.entities::<serde_json::Value>(ty, &missing, start),
+            client.versions::<serde_json::Value>(ty, &missing, start, end),
+        )?;
+
+        let tree = self.versions_tree(ty)?;
+        for version in initial.into_iter().chain(changes) {
+            tree.insert(version_key(version.entity_id, version.valid_from), serde_json::to_vec(&version.data)?)?;
+        }
+        // The versions have to be durable before the coverage that vouches for them is written
+        self.db.flush_async().await?;
+        for id in missing {
+            self.add_coverage(ty, id, start, end)?;
+        }
+        self.db.flush_async().await?;
+
+        Ok(())
+    }
+
+    // The version of this entity that was valid at `at`, if the store has it
+    pub fn version_at<T: DeserializeOwned>(&self, ty: &str, id: Uuid, at: DateTime<Utc>) -> anyhow::Result<Option<T>> {
+        let tree = self.versions_tree(ty)?;
+        // Every key for this entity starts with its id
+        tree.range(id.as_bytes().to_vec()..=version_key(id, at))
+            .next_back()
+            .map(|entry| Ok(serde_json::from_slice(&entry?.1)?))
+            .transpose()
+    }
+
+    fn require_version<T: DeserializeOwned>(&self, ty: &str, id: Uuid, at: DateTime<Utc>) -> anyhow::Result<T> {
+        self.version_at(ty, id, at)?
+            .ok_or_else(|| anyhow!("World store doesn't have {ty} {id} at {at}"))
+    }
+
+    // Every version of this entity that became valid in (start, end), in order
+    pub fn versions_between<T: DeserializeOwned>(&self, ty: &str, id: Uuid, start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<Vec<(DateTime<Utc>, T)>> {
+        let tree = self.versions_tree(ty)?;
+        tree.range((Bound::Excluded(version_key(id, start)), Bound::Excluded(version_key(id, end))))
+            .map(|entry| {
+                let (key, value) = entry?;
+                let valid_from = time_from_key(&key[16..])?;
+                Ok((valid_from, serde_json::from_slice(&value)?))
+            })
+            .collect()
+    }
+
+    // The version valid at start plus every version in the window
+    fn all_versions_in<T: DeserializeOwned>(&self, ty: &str, ids: &[Uuid], start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<Vec<T>> {
+        let mut versions = Vec::new();
+        for &id in ids {
+            versions.extend(self.version_at(ty, id, start)?);
+            versions.extend(self.versions_between(ty, id, start, end)?.into_iter().map(|(_, data)| data));
+        }
+        Ok(versions)
+    }
+
+    // Fetches everything needed to build a World at any time in [start, end). Anything the store
+    // already has for this window isn't refetched, so fragments that overlap share data.
+    pub async fn populate(&self, client: &ChronClient, start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<()> {
+        self.ensure_versions(client, "sim", &[Uuid::nil()], start, end).await?;
+        let leagues = self.all_versions_in::<SimData>("sim", &[Uuid::nil()], start, end)?.into_iter()
+            .map(|sim| sim.league)
+            .unique()
+            .collect_vec();
+
+        self.ensure_versions(client, "league", &leagues, start, end).await?;
+        let subleagues = self.all_versions_in::<LeagueData>("league", &leagues, start, end)?.into_iter()
+            .flat_map(|league| league.subleagues)
+            .unique()
+            .collect_vec();
+
+        self.ensure_versions(client, "subleague", &subleagues, start, end).await?;
+        let divisions = self.all_versions_in::<SubleagueData>("subleague", &subleagues, start, end)?.into_iter()
+            .flat_map(|subleague| subleague.divisions)
+            .unique()
+            .collect_vec();
+
+        self.ensure_versions(client, "division", &divisions, start, end).await?;
+        let mut team_ids = self.all_versions_in::<DivisionData>("division", &divisions, start, end)?.into_iter()
+            .flat_map(|division| division.teams)
+            .unique()
+            .collect_vec();
+        if team_ids.is_empty() {
+            // Dumps don't have to include the league structure. Without it, every team they have
+            // counts as being in the league.
+            team_ids = client.local_ids("team")?
+                .ok_or_else(|| anyhow!("Chronicler has no league structure between {start} and {end}"))?;
+        }
+
+        self.ensure_versions(client, "team", &team_ids, start, end).await?;
+        let teams = self.all_versions_in::<TeamRoster>("team", &team_ids, start, end)?;
+        // Anyone who's on any of these teams at any point in the window
+        let player_ids = teams.iter()
+            .flat_map(|team| team.all_player_ids())
+            .copied()
+            .unique()
+            .collect_vec();
+        let stadium_ids = teams.iter()
+            .filter_map(|team| team.stadium)
+            .unique()
+            .collect_vec();
+
+        tokio::try_join!(
+            self.ensure_versions(client, "player", &player_ids, start, end),
+            self.ensure_versions(client, "stadium", &stadium_ids, start, end),
+        )?;
+
+        Ok(())
+    }
+
+    // Every version of these entities that became valid in (start, end)
+    fn changes_between<T: DeserializeOwned>(&self, ty: &str, ids: &[Uuid], start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<Vec<(DateTime<Utc>, T)>> {
+        let mut changes = Vec::new();
+        for &id in ids {
+            changes.extend(self.versions_between(ty, id, start, end)?);
+        }
+        Ok(changes)
+    }
+
+    // Every version of these entities that was valid at any point in [start, end), as the JSON
+    // Chronicler sent, in id then time order. The version that was already valid at `start` is
+    // given `start` as its time, since that's when the window first sees it.
+    pub fn raw_versions_in(&self, ty: &str, ids: &[Uuid], start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<Vec<(DateTime<Utc>, serde_json::Value)>> {
+        let tree = self.versions_tree(ty)?;
+        let mut versions = Vec::new();
+        for &id in ids {
+            if let Some(entry) = tree.range(id.as_bytes().to_vec()..=version_key(id, start)).next_back() {
+                versions.push((start, serde_json::from_slice(&entry?.1)?));
+            }
+            for entry in tree.range((Bound::Excluded(version_key(id, start)), Bound::Excluded(version_key(id, end)))) {
+                let (key, value) = entry?;
+                versions.push((time_from_key(&key[16..])?, serde_json::from_slice(&value)?));
+            }
+        }
+        Ok(versions)
+    }
+
+    // Every team in the league at any point in [start, end), from the store alone. Call
+    // populate() for the same window first.
+    pub fn team_ids_in(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<Vec<Uuid>> {
+        let sims = self.all_versions_in::<SimData>("sim", &[Uuid::nil()], start, end)?;
+        if sims.is_empty() {
+            return self.stored_team_ids_until(end);
+        }
+        let leagues = sims.into_iter()
+            .map(|sim| sim.league)
+            .unique()
+            .collect_vec();
+        let subleagues = self.all_versions_in::<LeagueData>("league", &leagues, start, end)?.into_iter()
+            .flat_map(|league| league.subleagues)
+            .unique()
+            .collect_vec();
+        let divisions = self.all_versions_in::<SubleagueData>("subleague", &subleagues, start, end)?.into_iter()
+            .flat_map(|subleague| subleague.divisions)
+            .unique()
+            .collect_vec();
+        Ok(self.all_versions_in::<DivisionData>("division", &divisions, start, end)?.into_iter()
+            .flat_map(|division| division.teams)
+            .unique()
+            .collect_vec())
+    }
+
+    // Every team the store has a version of from `until` or earlier. Versions stay valid until the
+    // next one, so that's every team that exists at `until`. This is the league when there's no
+    // sim to walk down from, like with dumps that leave out the league structure.
+    fn stored_team_ids_until(&self, until: DateTime<Utc>) -> anyhow::Result<Vec<Uuid>> {
+        let tree = self.versions_tree("team")?;
+        let mut ids: Vec<Uuid> = Vec::new();
+        for key in tree.iter().keys() {
+            let id = Uuid::from_slice(&key?[..16])?;
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        }
+
+        let mut team_ids = Vec::new();
+        for id in ids {
+            if tree.range(id.as_bytes().to_vec()..=version_key(id, until)).next().is_some() {
+                team_ids.push(id);
+            }
+        }
+        Ok(team_ids)
+    }
+
+    // Everyone on any of these teams at any point in [start, end)
+    pub fn player_ids_in(&self, team_ids: &[Uuid], start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<Vec<Uuid>> {
+        Ok(self.all_versions_in::<TeamRoster>("team", team_ids, start, end)?.iter()
+            .flat_map(|team| team.all_player_ids())
+            .copied()
+            .unique()
+            .collect_vec())
+    }
+
+    // The stadiums of any of these teams at any point in [start, end)
+    fn stadium_ids_in(&self, team_ids: &[Uuid], start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<Vec<Uuid>> {
+        Ok(self.all_versions_in::<TeamRoster>("team", team_ids, start, end)?.iter()
+            .filter_map(|team| team.stadium)
+            .unique()
+            .collect_vec())
+    }
+
+    // Every change to the world that Chronicler saw in (start, end), in the order they happened.
+    // Only the entities populate() fetched for the window are looked at, so call it for the same
+    // window first.
+    pub fn updates_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<Vec<WorldUpdate>> {
+        let team_ids = self.team_ids_in(start, end)?;
+        let player_ids = self.player_ids_in(&team_ids, start, end)?;
+        let stadium_ids = self.stadium_ids_in(&team_ids, start, end)?;
+
+        let sims = self.changes_between::<SimData>("sim", &[Uuid::nil()], start, end)?.into_iter()
+            .map(|(valid_from, sim)| WorldUpdate {
+                valid_from,
+                change: WorldChange::Sim {
+                    sim: SimState { season: sim.season, day: sim.day, phase: sim.phase },
+                    league_mods: sim.attr,
+                },
+            });
+        let teams = self.changes_between::<Team>("team", &team_ids, start, end)?.into_iter()
+            .map(|(valid_from, team)| WorldUpdate { valid_from, change: WorldChange::Team(team) });
+        let players = self.changes_between::<Player>("player", &player_ids, start, end)?.into_iter()
+            .map(|(valid_from, player)| WorldUpdate { valid_from, change: WorldChange::Player(player) });
+        let stadiums = self.changes_between::<Stadium>("stadium", &stadium_ids, start, end)?.into_iter()
+            .map(|(valid_from, stadium)| WorldUpdate { valid_from, change: WorldChange::Stadium(stadium) });
+
+        // Stable, so simultaneous changes keep the sim-team-player-stadium order
+        Ok(sims.chain(teams).chain(players).chain(stadiums)
+            .sorted_by_key(|update| update.valid_from)
+            .collect())
+    }
+
+    // Builds the World as it was at `at` from the store alone
+    pub fn world_at(&self, at: DateTime<Utc>) -> anyhow::Result<World> {
+        let sim: Option<SimData> = self.version_at("sim", Uuid::nil(), at)?;
+        let mut team_ids = Vec::new();
+        if let Some(sim) = &sim {
+            let league: LeagueData = self.require_version("league", sim.league, at)?;
+            for subleague_id in league.subleagues {
+                let subleague: SubleagueData = self.require_version("subleague", subleague_id, at)?;
+                for division_id in subleague.divisions {
+                    let division: DivisionData = self.require_version("division", division_id, at)?;
+                    team_ids.extend(division.teams);
+                }
+            }
+        } else {
+            team_ids = self.stored_team_ids_until(at)?;
+        }
+        if team_ids.is_empty() {
+            return Err(anyhow!("World store has no teams at {at}"));
+        }
+
+        let teams = team_ids.into_iter()
+            .map(|id| Ok((id, self.require_version::<Team>("team", id, at)?)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        let players = teams.values()
+            .flat_map(|team| team.all_player_ids())
+            .unique()
+            .map(|&id| Ok((id, self.require_version::<Player>("player", id, at)?)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        let stadiums = teams.values()
+            .filter_map(|team| team.stadium)
+            .unique()
+            .map(|id| Ok((id, self.require_version::<Stadium>("stadium", id, at)?)))
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+
+        let sim_state = sim.as_ref().map(|sim| SimState {
+            season: sim.season,
+            day: sim.day,
+            phase: sim.phase,
+        });
+        Ok(World {
+            sim: sim_state,
+            teams,
+            players,
+            stadiums,
+            league_mods: sim.map(|sim| sim.attr).unwrap_or_default(),
+            newcomers: HashMap::new(),
+        })
+    }
+
+    // Everyone who's on one of the world's teams at some point in (start, end) but isn't in the
+    // world yet, as of the first version of them in the window
+    pub fn newcomers(&self, world: &World, start: DateTime<Utc>, end: DateTime<Utc>) -> anyhow::Result<HashMap<Uuid, Player>> {
+        let team_ids = world.teams.keys().copied().collect_vec();
+        let newcomer_ids = self.all_versions_in::<TeamRoster>("team", &team_ids, start, end)?.iter()
+            .flat_map(|team| team.all_player_ids())
+            .filter(|id| !world.players.contains_key(id))
+            .copied()
+            .unique()
+            .collect_vec();
+
+        let mut newcomers = HashMap::new();
+        for id in newcomer_ids {
+            let first_version = match self.versions_between::<Player>("player", id, start, end)?.into_iter().next() {
+                Some((_, player)) => Some(player),
+                None => self.version_at("player", id, start)?,
+            };
+            if let Some(player) = first_version {
+                newcomers.insert(id, player);
+            }
+        }
+        Ok(newcomers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/chron");
+    const TEAM_ONE: Uuid = Uuid::from_u128(0x00000000_0000_4000_8000_000000000400);
+    const TEAM_TWO: Uuid = Uuid::from_u128(0x00000000_0000_4000_8000_000000000401);
+    const STADIUM: Uuid = Uuid::from_u128(0x00000000_0000_4000_8000_000000000500);
+    const BATTER_ONE: Uuid = Uuid::from_u128(0x00000000_0000_4000_8000_000000000600);
+    const NEWCOMER: Uuid = Uuid::from_u128(0x00000000_0000_4000_8000_000000000604);
+
+    fn time(hour: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2021, 4, 12, hour, min, 0).unwrap()
+    }
+
+    // A fresh store in the temp dir, so tests don't share state
+    fn temp_store() -> (WorldStore, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("world-store-test-{}", Uuid::new_v4()));
+        let store = WorldStore::open(path.to_str().unwrap()).unwrap();
+        (store, path)
+    }
+
+    #[tokio::test]
+    async fn world_from_fixtures() {
+        let (store, path) = temp_store();
+        let client = ChronClient::from_fixtures(FIXTURES);
+        store.populate(&client, time(15, 0), time(16, 0)).await.unwrap();
+
+        let world = store.world_at(time(15, 0)).unwrap();
+        assert_eq!(world.sim.as_ref().unwrap().season, 14);
+        assert_eq!(world.teams.len(), 2);
+        assert!(world.teams.contains_key(&TEAM_ONE) && world.teams.contains_key(&TEAM_TWO));
+        assert_eq!(world.players.len(), 4);
+        assert_eq!(world.stadiums.keys().collect_vec(), vec![&STADIUM]);
+        assert_eq!(world.players[&BATTER_ONE].musclitude, 0.5);
+
+        // The shadow who joins partway through the window was fetched too, and later worlds see
+        // both them and the batter's stat change
+        let later = store.world_at(time(15, 45)).unwrap();
+        assert_eq!(later.players.len(), 5);
+        assert!(later.players.contains_key(&NEWCOMER));
+        assert_eq!(later.players[&BATTER_ONE].musclitude, 0.75);
+
+        drop(store);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn raw_versions_in_window() {
+        let (store, path) = temp_store();
+        let client = ChronClient::from_fixtures(FIXTURES);
+        store.populate(&client, time(15, 0), time(16, 0)).await.unwrap();
+
+        let team_ids = store.team_ids_in(time(15, 0), time(16, 0)).unwrap();
+        assert_eq!(team_ids.len(), 2);
+        // Both teams as of the start of the window, plus the first team's mid-window change
+        let teams = store.raw_versions_in("team", &team_ids, time(15, 0), time(16, 0)).unwrap();
+        assert_eq!(teams.len(), 3);
+        assert!(teams.iter().all(|(time_seen, _)| *time_seen >= time(15, 0)));
+        assert_eq!(store.player_ids_in(&team_ids, time(15, 0), time(16, 0)).unwrap().len(), 5);
+
+        drop(store);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn updates_only_for_the_league() {
+        let (store, path) = temp_store();
+        let client = ChronClient::from_fixtures(FIXTURES);
+        store.populate(&client, time(15, 0), time(16, 0)).await.unwrap();
+        // Someone who isn't on any of the league's teams, left over from some other window
+        let stranger = Uuid::from_u128(0x00000000_0000_4000_8000_000000000699);
+        store.versions_tree("player").unwrap().insert(version_key(stranger, time(15, 10)), b"{}".to_vec()).unwrap();
+
+        // The first team's change and the batter's, in order
+        let updates = store.updates_between(time(15, 0), time(16, 0)).unwrap();
+        assert_eq!(updates.iter().map(|update| update.valid_from).collect_vec(), vec![time(15, 20), time(15, 30)]);
+        assert!(matches!(&updates[0].change, WorldChange::Team(team) if team.id == TEAM_ONE));
+        assert!(matches!(&updates[1].change, WorldChange::Player(player) if player.id == BATTER_ONE));
+
+        drop(store);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+
+    // Writes the team, player and stadium fixtures out as a dump without the league structure.
+    // Teams go in NDJSON, with a repeated line like overlapping dumps have, and the rest in JSON.
+    fn write_partial_dump(dir: &std::path::Path) {
+        std::fs::create_dir_all(dir).unwrap();
+        for ty in ["team", "player", "stadium"] {
+            let mut versions = Vec::new();
+            for entry in std::fs::read_dir(format!("{FIXTURES}/{ty}")).unwrap() {
+                let text = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+                versions.extend(serde_json::from_str::<Vec<serde_json::Value>>(&text).unwrap());
+            }
+            if ty == "team" {
+                versions.push(versions[0].clone());
+                let lines = versions.iter().map(|version| version.to_string()).join("\n");
+                std::fs::write(dir.join("team.ndjson"), lines).unwrap();
+            } else {
+                std::fs::write(dir.join(format!("{ty}.json")), serde_json::to_string(&versions).unwrap()).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn world_from_dump_without_league_structure() {
+        let (store, path) = temp_store();
+        let dump_dir = path.with_extension("dump");
+        write_partial_dump(&dump_dir);
+        let client = ChronClient::from_dump(&dump_dir).unwrap();
+        store.populate(&client, time(15, 0), time(16, 0)).await.unwrap();
+
+        // Every team in the dump is in the league
+        let world = store.world_at(time(15, 0)).unwrap();
+        assert!(world.sim.is_none());
+        assert_eq!(world.teams.len(), 2);
+        assert_eq!(world.players.len(), 4);
+        assert_eq!(store.team_ids_in(time(15, 0), time(16, 0)).unwrap().len(), 2);
+        assert_eq!(store.world_at(time(15, 45)).unwrap().players[&BATTER_ONE].musclitude, 0.75);
+
+        drop(store);
+        std::fs::remove_dir_all(path).unwrap();
+        std::fs::remove_dir_all(dump_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn world_outside_populated_window() {
+        let (store, path) = temp_store();
+        let client = ChronClient::from_fixtures(FIXTURES);
+        store.populate(&client, time(15, 0), time(16, 0)).await.unwrap();
+
+        // Nothing was fetched for before the fixtures start
+        assert!(store.world_at(Utc.with_ymd_and_hms(2021, 4, 11, 0, 0, 0).unwrap()).is_err());
+
+        drop(store);
+        std::fs::remove_dir_all(path).unwrap();
+    }
+}